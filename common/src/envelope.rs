@@ -0,0 +1,98 @@
+//! Common response envelope for procedure success payloads.
+//!
+//! Every procedure wraps its output in an [`Envelope`] so that a response
+//! on its own says which build produced it and which embedded data it used.
+//! Support tickets can then be matched to a deployment without guesswork.
+
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// Build-time facts about the procedure that produced a response.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Provenance {
+    /// Crate version of the procedure (`CARGO_PKG_VERSION`).
+    pub version: &'static str,
+    /// SHA-256 of the embedded data set, if the procedure ships one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_hash: Option<&'static str>,
+}
+
+/// Success payload wrapper shared by all procedures.
+#[derive(Serialize, Debug)]
+pub struct Envelope<T> {
+    #[serde(flatten)]
+    pub provenance: Provenance,
+    /// Trace ID of the request, for correlating with server logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Wall time spent inside the procedure, in microseconds.
+    pub duration_us: u64,
+    /// Procedure-specific payload.
+    pub data: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wrap `data`, measuring the duration from `started` until now.
+    pub fn new(provenance: Provenance, started: Instant, data: T) -> Self {
+        let duration_us = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+        Self {
+            provenance,
+            trace_id: None,
+            duration_us,
+            data,
+        }
+    }
+
+    /// Attach the request's trace ID.
+    pub fn with_trace_id(mut self, trace_id: String) -> Self {
+        self.trace_id = Some(trace_id);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_serializes_flat_provenance() {
+        let provenance = Provenance {
+            version: "1.2.3",
+            data_hash: Some("abcd"),
+        };
+        let envelope = Envelope::new(provenance, Instant::now(), 42u32);
+
+        let json: serde_json::Value = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["version"], "1.2.3");
+        assert_eq!(json["data_hash"], "abcd");
+        assert_eq!(json["data"], 42);
+        assert!(json["duration_us"].is_u64());
+    }
+
+    #[test]
+    fn test_missing_data_hash_is_omitted() {
+        let provenance = Provenance {
+            version: "0.1.0",
+            data_hash: None,
+        };
+        let envelope = Envelope::new(provenance, Instant::now(), ());
+
+        let json: serde_json::Value = serde_json::to_value(&envelope).unwrap();
+        assert!(json.get("data_hash").is_none());
+        assert!(json.get("trace_id").is_none());
+    }
+
+    #[test]
+    fn test_trace_id_is_serialized() {
+        let provenance = Provenance {
+            version: "0.1.0",
+            data_hash: None,
+        };
+        let envelope =
+            Envelope::new(provenance, Instant::now(), ()).with_trace_id("t-1".to_string());
+
+        let json: serde_json::Value = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["trace_id"], "t-1");
+    }
+}
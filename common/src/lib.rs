@@ -0,0 +1,10 @@
+//! Shared building blocks for the procedures in this workspace.
+//!
+//! Each procedure is its own WASM module, so anything that must look the
+//! same across all of them (response shape, provenance fields) lives here
+//! instead of being copied into every crate.
+
+pub mod envelope;
+pub mod trace;
+
+pub use envelope::{Envelope, Provenance};
@@ -0,0 +1,72 @@
+//! Request trace IDs.
+//!
+//! Clients may pass their own trace ID so a response can be matched to
+//! server logs. When they don't, the procedure generates one and returns it.
+//! Client IDs end up in every log line, so they are capped at
+//! [`MAX_TRACE_ID_LEN`] characters and replaced if they hold control
+//! characters (a newline would forge log lines).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Longest client trace ID kept; longer ones are truncated.
+pub const MAX_TRACE_ID_LEN: usize = 64;
+
+/// Per-instance counter so IDs generated within the same nanosecond differ.
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Use the client-supplied trace ID, truncated to [`MAX_TRACE_ID_LEN`]
+/// characters, or generate one when it is absent, blank or contains control
+/// characters.
+pub fn resolve(trace_id: Option<String>) -> String {
+    match trace_id {
+        Some(id) if !id.trim().is_empty() && !id.chars().any(char::is_control) => {
+            id.chars().take(MAX_TRACE_ID_LEN).collect()
+        }
+        _ => generate(),
+    }
+}
+
+/// Generate a 16-hex-character trace ID.
+///
+/// Not cryptographically random; only needs to be unique enough to find
+/// a request in the logs.
+pub fn generate() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    // Spread the counter over the high bits so consecutive IDs look distinct
+    format!("{:016x}", nanos ^ count.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_keeps_client_id() {
+        assert_eq!(resolve(Some("abc-123".to_string())), "abc-123");
+    }
+
+    #[test]
+    fn test_resolve_generates_when_missing_or_blank() {
+        assert_eq!(resolve(None).len(), 16);
+        assert_eq!(resolve(Some("  ".to_string())).len(), 16);
+    }
+
+    #[test]
+    fn test_resolve_sanitizes_client_id() {
+        assert_eq!(resolve(Some("x".repeat(200))), "x".repeat(MAX_TRACE_ID_LEN));
+        let forged = resolve(Some("abc\ntrace_id=other".to_string()));
+        assert_eq!(forged.len(), 16);
+        assert!(!forged.contains('\n'));
+        assert_eq!(resolve(Some("a\tb".to_string())).len(), 16);
+    }
+
+    #[test]
+    fn test_generated_ids_differ() {
+        assert_ne!(generate(), generate());
+    }
+}
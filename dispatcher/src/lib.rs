@@ -0,0 +1,69 @@
+//! One deployed WASM module hosting several procedures.
+//!
+//! The request's `method` selects the procedure and `params` holds its usual
+//! input:
+//!
+//! ```json
+//! { "method": "leader_routing", "params": { "mode": "plan" } }
+//! ```
+//!
+//! Each hosted crate is linked with its `procedure` feature off, so this is
+//! the module's only entry point and the geo data is embedded once.
+
+use serde::{Deserialize, Serialize};
+use zela_std::{CustomProcedure, RpcError, zela_custom_procedure};
+
+use leader_routing::{ErrorDetail, LeaderRouting};
+#[cfg(target_arch = "wasm32")]
+use priority_fees::PriorityFees;
+
+// Binding for the dispatcher procedure.
+pub struct Dispatcher;
+
+/// Procedure selected by `method`, with its own parameters.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Request {
+    LeaderRouting(<LeaderRouting as CustomProcedure>::Params),
+    // priority_fees only implements the procedure for the WASM target
+    #[cfg(target_arch = "wasm32")]
+    PriorityFees(<PriorityFees as CustomProcedure>::Params),
+}
+
+/// Response of whichever procedure ran, unchanged.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Response {
+    LeaderRouting(<LeaderRouting as CustomProcedure>::SuccessData),
+    #[cfg(target_arch = "wasm32")]
+    PriorityFees(<PriorityFees as CustomProcedure>::SuccessData),
+}
+
+impl CustomProcedure for Dispatcher {
+    type Params = Request;
+    // Only leader routing attaches error data
+    type ErrorData = ErrorDetail;
+    type SuccessData = Response;
+
+    async fn run(params: Self::Params) -> Result<Self::SuccessData, RpcError<Self::ErrorData>> {
+        log::debug!("params: {params:?}");
+
+        match params {
+            Request::LeaderRouting(p) => LeaderRouting::run(p).await.map(Response::LeaderRouting),
+            #[cfg(target_arch = "wasm32")]
+            Request::PriorityFees(p) => <PriorityFees as CustomProcedure>::run(p)
+                .await
+                .map(Response::PriorityFees)
+                .map_err(|e| RpcError {
+                    code: e.code,
+                    message: e.message,
+                    data: None,
+                }),
+        }
+    }
+
+    const LOG_MAX_LEVEL: log::LevelFilter = log::LevelFilter::Debug;
+}
+
+// This is an essential macro-call that enables us to run a procedure
+zela_custom_procedure!(Dispatcher);
@@ -1,84 +1,814 @@
 //! Build script for leader_routing.
 //!
 //! ## What This Generates
-//! - `phf_geo.rs`: PHF map for O(1) validator -> region lookup
+//! - `region_gen.rs`: the `Region` enum, its u8 codes, geo labels and routing
+//!   destinations, from `data/regions.json` (checked in, always required)
+//! - `phf_geo.rs`: PHF map for O(1) validator -> region lookup, plus the
+//!   SHA-256 of the source data (reported as response provenance)
+//! - `phf_identity.rs`: PHF map of validator names and stake, from the
+//!   optional `data/validator_info.json` (empty without it)
+//! - `phf_tpu.rs`: PHF map of validator TPU endpoints, from the optional
+//!   gossip snapshot `data/leader_tpu.json` (empty without it)
+//! - `geo_meta.rs`: when the geo data was generated, from the optional
+//!   `data/leader_geo_meta.json`, and the staleness limit
 //!
 //! ## Prerequisites
 //! Run this Python script before building:
-//! - `python scripts/precompute_geo.py` -> `data/leader_geo.json`
+//! - `python scripts/precompute_geo.py` -> `data/leader_geo.json`,
+//!   `data/leader_geo_meta.json`, `data/leader_tpu.json`
+//! - optionally `python scripts/fetch_validator_info.py` -> `data/validator_info.json`
 //!
-//! Without this file, stub data is generated.
+//! Without the geo file, stub data is generated.
+//!
+//! ## Environment
+//! - `LEADER_ROUTING_FALLBACK_REGION`: where Unknown validators route (region
+//!   name, short code or alias), overriding the Unknown entry's `routes_to`
+//!   and the registry `default` (`Region::DEFAULT`)
+//! - `LEADER_ROUTING_CLUSTER`: read the validator data files from
+//!   `data/<cluster>/` (e.g. `testnet`) instead of `data/`; `regions.json`
+//!   is shared by all clusters. Fails without `data/<cluster>/leader_geo.json`
+//! - `LEADER_ROUTING_REQUIRE_DATA`: fail instead of generating stub data
+//! - `LEADER_ROUTING_MAX_DATA_AGE_HOURS`: refuse requests once the geo data is
+//!   older than this (requires `data/leader_geo_meta.json`)
 
-use std::collections::HashMap;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
 use std::{env, fs, path::Path};
 
+/// Region list as defined in `data/regions.json`.
+#[derive(Deserialize)]
+struct RegionRegistry {
+    /// Region that codes outside the registry decode to.
+    default: String,
+    /// Regions in code order: a region's u8 code is its index.
+    regions: Vec<RegionDef>,
+}
+
+#[derive(Deserialize)]
+struct RegionDef {
+    name: String,
+    /// Canonical infrastructure code (e.g. "fra1"). Required except for Unknown.
+    short_code: Option<String>,
+    /// Extra names accepted on input, matched case-insensitively.
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// Where the region's servers are. Required except for Unknown.
+    location: Option<Location>,
+    /// Where traffic for this region is sent. Defaults to the region itself.
+    routes_to: Option<String>,
+    /// Optional doc comment for the generated enum variant.
+    doc: Option<String>,
+    /// Server location. Required for every region except Unknown.
+    lat: Option<f64>,
+    lon: Option<f64>,
+    /// Relative capacity used by region selection; lower sheds traffic.
+    #[serde(default = "default_weight")]
+    weight: f64,
+    /// Temporarily take the region out of selection entirely.
+    #[serde(default)]
+    drain: bool,
+    /// Measured round-trip times to other regions, in ms. Each pair is
+    /// listed once, on either side; the matrix is symmetric.
+    #[serde(default)]
+    rtt_ms: BTreeMap<String, f64>,
+    /// Ordered backup regions for when this one is down. Unknown has none.
+    #[serde(default)]
+    failover: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Location {
+    continent: String,
+    /// ISO 3166-1 alpha-2 country code.
+    country: String,
+    city: String,
+}
+
+/// One validator in `validator_info.json`, keyed by identity pubkey.
+#[derive(Deserialize)]
+struct ValidatorInfo {
+    /// Name published on chain with `solana validator-info`, if any.
+    name: Option<String>,
+    /// Activated stake across the identity's vote accounts, in SOL.
+    stake_sol: f64,
+    /// Vote accounts of the identity, highest stake first.
+    #[serde(default)]
+    vote_accounts: Vec<String>,
+}
+
+/// `leader_geo_meta.json`: how the geo data was produced.
+#[derive(Deserialize)]
+struct GeoMeta {
+    /// Unix time (seconds) precompute_geo.py wrote the geo data.
+    generated_at: u64,
+}
+
+/// One validator in `leader_tpu.json`: socket addresses from gossip.
+#[derive(Deserialize)]
+struct TpuEntry {
+    tpu: Option<String>,
+    tpu_quic: Option<String>,
+    tpu_forwards: Option<String>,
+}
+
+/// One validator in `leader_geo.json`.
+///
+/// Older files map pubkey -> region name; current ones also carry the ISO
+/// country code, city, coordinates and network (ASN, hosting provider) the
+/// validator's IP geolocated to.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GeoEntry {
+    Region(String),
+    Located {
+        region: String,
+        country: Option<String>,
+        lat: Option<f64>,
+        lon: Option<f64>,
+        city: Option<String>,
+        asn: Option<u32>,
+        provider: Option<String>,
+    },
+}
+
+impl GeoEntry {
+    fn region(&self) -> &str {
+        match self {
+            GeoEntry::Region(region) | GeoEntry::Located { region, .. } => region,
+        }
+    }
+
+    /// Country code, if present and a well-formed ISO 3166-1 alpha-2 code.
+    fn country(&self) -> Option<&str> {
+        match self {
+            GeoEntry::Located {
+                country: Some(country),
+                ..
+            } if country.len() == 2 && country.bytes().all(|b| b.is_ascii_uppercase()) => {
+                Some(country)
+            }
+            _ => None,
+        }
+    }
+
+    /// City name, if present and non-empty.
+    fn city(&self) -> Option<&str> {
+        match self {
+            GeoEntry::Located {
+                city: Some(city), ..
+            } if !city.is_empty() => Some(city),
+            _ => None,
+        }
+    }
+
+    /// Autonomous system number of the validator's IP, if known.
+    fn asn(&self) -> Option<u32> {
+        match self {
+            GeoEntry::Located { asn, .. } => *asn,
+            GeoEntry::Region(_) => None,
+        }
+    }
+
+    /// Hosting provider / datacenter operator, if present and non-empty.
+    fn provider(&self) -> Option<&str> {
+        match self {
+            GeoEntry::Located {
+                provider: Some(provider),
+                ..
+            } if !provider.is_empty() => Some(provider),
+            _ => None,
+        }
+    }
+
+    /// Latitude and longitude in degrees, if both are present and in range.
+    fn coordinates(&self) -> Option<(f64, f64)> {
+        match self {
+            GeoEntry::Located {
+                lat: Some(lat),
+                lon: Some(lon),
+                ..
+            } if (-90.0..=90.0).contains(lat) && (-180.0..=180.0).contains(lon) => {
+                Some((*lat, *lon))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl RegionRegistry {
+    fn load(path: &str) -> Self {
+        let json = fs::read_to_string(path).expect("Failed to read regions.json");
+        let mut registry: RegionRegistry =
+            serde_json::from_str(&json).expect("Failed to parse regions.json");
+        if let Ok(fallback) = env::var("LEADER_ROUTING_FALLBACK_REGION") {
+            registry.set_fallback(&fallback);
+        }
+        registry.validate();
+        registry
+    }
+
+    /// Route Unknown, and unmapped region codes, to the region `name`
+    /// resolves to (deployment override of Unknown's `routes_to` and of
+    /// `default`).
+    fn set_fallback(&mut self, name: &str) {
+        let lowercase = name.trim().to_lowercase();
+        let target = self
+            .regions
+            .iter()
+            .find(|r| r.input_names().contains(&lowercase))
+            .map(|r| r.name.clone())
+            .unwrap_or_else(|| panic!("LEADER_ROUTING_FALLBACK_REGION: unknown region {:?}", name));
+        let unknown = self
+            .regions
+            .iter_mut()
+            .find(|r| r.name == "Unknown")
+            .expect("regions.json: an \"Unknown\" region is required");
+        unknown.routes_to = Some(target.clone());
+        self.default = target;
+    }
+
+    /// Panics with a descriptive message if the registry is inconsistent.
+    fn validate(&self) {
+        assert!(
+            self.regions.len() <= u8::MAX as usize,
+            "regions.json: too many regions for a u8 code"
+        );
+        assert!(
+            self.code("Unknown").is_some(),
+            "regions.json: an \"Unknown\" region is required"
+        );
+        for (i, region) in self.regions.iter().enumerate() {
+            assert!(
+                !self.regions[..i].iter().any(|r| r.name == region.name),
+                "regions.json: duplicate region {}",
+                region.name
+            );
+            let target = region.destination();
+            assert!(
+                self.code(target).is_some() && target != "Unknown",
+                "regions.json: {} routes to invalid region {}",
+                region.name,
+                target
+            );
+            assert!(
+                region.coordinates().is_some() == (region.name != "Unknown"),
+                "regions.json: {} needs lat/lon (and Unknown must have none)",
+                region.name
+            );
+            assert!(
+                region.location.is_some() == (region.name != "Unknown"),
+                "regions.json: {} needs a location (and Unknown must have none)",
+                region.name
+            );
+            if let Some(location) = &region.location {
+                assert!(
+                    location.country.len() == 2
+                        && location.country.bytes().all(|b| b.is_ascii_uppercase()),
+                    "regions.json: {} country must be an ISO 3166-1 alpha-2 code",
+                    region.name
+                );
+            }
+            for (j, backup) in region.failover.iter().enumerate() {
+                assert!(
+                    self.code(backup).is_some()
+                        && backup != "Unknown"
+                        && *backup != region.name
+                        && !region.failover[..j].contains(backup),
+                    "regions.json: {} has invalid failover region {}",
+                    region.name,
+                    backup
+                );
+            }
+            assert!(
+                region.name != "Unknown" || region.failover.is_empty(),
+                "regions.json: Unknown uses routes_to, not failover"
+            );
+            assert!(
+                region.weight.is_finite() && region.weight > 0.0,
+                "regions.json: {} weight must be positive",
+                region.name
+            );
+        }
+        for region in &self.regions {
+            for (to, rtt) in &region.rtt_ms {
+                assert!(
+                    self.code(to).is_some() && to != "Unknown" && *to != region.name,
+                    "regions.json: {} has RTT to invalid region {}",
+                    region.name,
+                    to
+                );
+                assert!(
+                    rtt.is_finite() && *rtt >= 0.0,
+                    "regions.json: invalid RTT {} -> {}",
+                    region.name,
+                    to
+                );
+                let reverse = self.regions.iter().find(|r| r.name == *to).unwrap();
+                assert!(
+                    !reverse.rtt_ms.contains_key(&region.name),
+                    "regions.json: RTT between {} and {} is listed twice",
+                    region.name,
+                    to
+                );
+            }
+        }
+        let mut seen = Vec::new();
+        for region in &self.regions {
+            assert!(
+                region.short_code.is_some() == (region.name != "Unknown"),
+                "regions.json: {} needs a short_code (and Unknown must have none)",
+                region.name
+            );
+            for alias in region.input_names() {
+                assert!(
+                    !seen.contains(&alias),
+                    "regions.json: {:?} names more than one region",
+                    alias
+                );
+                seen.push(alias);
+            }
+        }
+        assert!(
+            self.regions
+                .iter()
+                .any(|r| !r.drain && r.coordinates().is_some()),
+            "regions.json: every region is drained"
+        );
+        assert!(
+            self.code(&self.default).is_some() && self.default != "Unknown",
+            "regions.json: invalid default region {}",
+            self.default
+        );
+    }
+
+    /// u8 code for a region name, if it is in the registry.
+    fn code(&self, name: &str) -> Option<u8> {
+        self.regions
+            .iter()
+            .position(|r| r.name == name)
+            .map(|i| i as u8)
+    }
+}
+
+impl RegionDef {
+    fn destination(&self) -> &str {
+        self.routes_to.as_deref().unwrap_or(&self.name)
+    }
+
+    fn coordinates(&self) -> Option<(f64, f64)> {
+        Some((self.lat?, self.lon?))
+    }
+
+    /// Every lowercased name that resolves to this region on input.
+    fn input_names(&self) -> Vec<String> {
+        let mut names = vec![self.name.to_lowercase()];
+        names.extend(self.short_code.iter().map(|c| c.to_lowercase()));
+        names.extend(self.aliases.iter().map(|a| a.to_lowercase()));
+        // geo_label() form, e.g. "europe/frankfurt"
+        if let Some(l) = &self.location {
+            names.push(format!("{}/{}", l.continent, l.city).to_lowercase());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
 fn main() {
-    println!("cargo:rerun-if-changed=data/leader_geo.json");
+    println!("cargo:rerun-if-changed=data/regions.json");
+    println!("cargo:rerun-if-env-changed=LEADER_ROUTING_CLUSTER");
+    println!("cargo:rerun-if-env-changed=LEADER_ROUTING_FALLBACK_REGION");
+    println!("cargo:rerun-if-env-changed=LEADER_ROUTING_MAX_DATA_AGE_HOURS");
 
-    let geo_path = "data/leader_geo.json";
+    // Validator data is per cluster; the region registry is shared
+    let cluster = env::var("LEADER_ROUTING_CLUSTER").ok();
+    let data_dir = match &cluster {
+        Some(cluster) => {
+            assert!(
+                !cluster.is_empty()
+                    && cluster
+                        .bytes()
+                        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-'),
+                "LEADER_ROUTING_CLUSTER must be a lowercase name like \"testnet\""
+            );
+            format!("data/{}", cluster)
+        }
+        None => "data".to_string(),
+    };
+    let data_file = |name: &str| {
+        let path = format!("{}/{}", data_dir, name);
+        println!("cargo:rerun-if-changed={}", path);
+        path
+    };
+    // A watched path that does not exist leaves the build script dirty on
+    // every build, so optional files are only watched once present; watching
+    // the directory picks up their creation.
+    let optional_data_file = |name: &str| {
+        let path = format!("{}/{}", data_dir, name);
+        if Path::new(&path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+        path
+    };
+    if Path::new(&data_dir).is_dir() {
+        println!("cargo:rerun-if-changed={}", data_dir);
+    }
 
-    if !Path::new(geo_path).exists() {
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    let out_path = Path::new(&out_dir);
+
+    let registry = RegionRegistry::load("data/regions.json");
+    generate_region_registry(&registry, out_path);
+    generate_validator_info_phf(&optional_data_file("validator_info.json"), out_path);
+    generate_tpu_phf(&optional_data_file("leader_tpu.json"), out_path);
+    generate_geo_meta(
+        &optional_data_file("leader_geo_meta.json"),
+        cluster.as_deref(),
+        out_path,
+    );
+
+    let geo_path = data_file("leader_geo.json");
+
+    // Stub data for a named cluster would route every leader as Unknown
+    assert!(
+        cluster.is_none() || Path::new(&geo_path).exists(),
+        "LEADER_ROUTING_CLUSTER={} needs {}; generate it with \
+         python scripts/precompute_geo.py <RPC_URL> {}",
+        cluster.as_deref().unwrap_or_default(),
+        geo_path,
+        geo_path
+    );
+
+    if !Path::new(&geo_path).exists() {
         // CI mode: fail if stub data would be used
         if env::var("LEADER_ROUTING_REQUIRE_DATA").is_ok() {
             eprintln!("=== BUILD FAILED: Missing data file ===");
-            eprintln!("  - data/leader_geo.json is missing");
+            eprintln!("  - {} is missing", geo_path);
             eprintln!();
             eprintln!("To fix:");
             eprintln!("  1. pip install requests base58");
-            eprintln!("  2. python scripts/precompute_geo.py");
+            eprintln!(
+                "  2. python scripts/precompute_geo.py [RPC_URL] {}",
+                geo_path
+            );
             eprintln!("  3. cargo build");
             panic!("Data file required in CI mode");
         }
 
-        create_stub_files();
+        create_stub_files(out_path);
         return;
     }
 
-    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
-    let out_path = Path::new(&out_dir);
-
     // Process leader_geo.json
-    let geo_json = fs::read_to_string(geo_path)
-        .expect("Failed to read leader_geo.json");
-    let geo_map: HashMap<String, String> = serde_json::from_str(&geo_json)
-        .expect("Failed to parse leader_geo.json");
+    let geo_json = fs::read_to_string(&geo_path).expect("Failed to read leader_geo.json");
+    let geo_map: HashMap<String, GeoEntry> =
+        serde_json::from_str(&geo_json).expect("Failed to parse leader_geo.json");
+
+    let geo_hash = sha256_hex(geo_json.as_bytes());
 
-    generate_validator_to_region_phf(&geo_map, out_path);
+    generate_validator_to_region_phf(&registry, &geo_map, &geo_hash, out_path);
 }
 
-/// Map region name to u8 code for compact storage.
-fn region_to_u8(region: &str) -> u8 {
-    match region {
-        "Frankfurt" => 0,
-        "Dubai" => 1,
-        "NewYork" => 2,
-        "Tokyo" => 3,
-        _ => 4, // Unknown
+/// Hex-encoded SHA-256, matching `sha256sum data/leader_geo.json`.
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Generate the `Region` enum and its lookup tables from the registry.
+///
+/// Everything that depends on the region list lives here, so adding a
+/// region only means editing `data/regions.json`.
+fn generate_region_registry(registry: &RegionRegistry, out_path: &Path) {
+    let gen_path = out_path.join("region_gen.rs");
+    let mut file = fs::File::create(&gen_path).expect("Failed to create region_gen.rs");
+    let names: Vec<&str> = registry.regions.iter().map(|r| r.name.as_str()).collect();
+
+    writeln!(file, "/// Zela server regions.").unwrap();
+    writeln!(file, "///").unwrap();
+    writeln!(file, "/// Generated by build.rs from data/regions.json.").unwrap();
+    writeln!(
+        file,
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]"
+    )
+    .unwrap();
+    writeln!(file, "pub enum Region {{").unwrap();
+    for region in &registry.regions {
+        if let Some(doc) = &region.doc {
+            writeln!(file, "    /// {}", doc).unwrap();
+        }
+        if region.routes_to.is_some() {
+            writeln!(file, "    /// Routes to {}.", region.destination()).unwrap();
+        }
+        writeln!(file, "    {},", region.name).unwrap();
+    }
+    writeln!(file, "}}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(file, "impl Region {{").unwrap();
+    writeln!(file, "    /// Every region, in code order.").unwrap();
+    writeln!(
+        file,
+        "    pub const ALL: [Region; {}] = [{}];",
+        names.len(),
+        names
+            .iter()
+            .map(|n| format!("Region::{}", n))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+    .unwrap();
+    writeln!(file).unwrap();
+    writeln!(
+        file,
+        "    /// Default fallback region for unknown validator locations."
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "    pub const DEFAULT: Region = Region::{};",
+        registry.default
+    )
+    .unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Structured location of the region; None for Unknown."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn location(&self) -> Option<GeoLabel> {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        let location = match &region.location {
+            Some(l) => format!(
+                "Some(GeoLabel {{ continent: {:?}, country: {:?}, city: {:?} }})",
+                l.continent, l.country, l.city
+            ),
+            None => "None".to_string(),
+        };
+        writeln!(file, "            Region::{} => {},", region.name, location).unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Human-readable geographic label for the region."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn geo_label(&self) -> &'static str {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        // Same format as GeoLabel's Display impl
+        let label = match &region.location {
+            Some(l) => format!("{}/{}", l.continent, l.city),
+            None => "UNKNOWN".to_string(),
+        };
+        writeln!(file, "            Region::{} => {:?},", region.name, label).unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(file, "    /// Get the routing destination for this region.").unwrap();
+    writeln!(file, "    pub fn routing_destination(&self) -> Region {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        writeln!(
+            file,
+            "            Region::{} => Region::{},",
+            region.name,
+            region.destination()
+        )
+        .unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Server location as (latitude, longitude); None for Unknown."
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "    pub fn coordinates(&self) -> Option<(f64, f64)> {{"
+    )
+    .unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        let coords = match region.coordinates() {
+            Some((lat, lon)) => format!("Some(({:?}, {:?}))", lat, lon),
+            None => "None".to_string(),
+        };
+        writeln!(file, "            Region::{} => {},", region.name, coords).unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Relative capacity weight used by region selection (1.0 = nominal)."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn weight(&self) -> f64 {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        writeln!(
+            file,
+            "            Region::{} => {:?},",
+            region.name, region.weight
+        )
+        .unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Whether the region is drained and receives no new traffic."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn is_drained(&self) -> bool {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        writeln!(
+            file,
+            "            Region::{} => {},",
+            region.name, region.drain
+        )
+        .unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Measured round-trip time to `to` in ms, if known."
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "    pub fn rtt_ms(&self, to: Region) -> Option<f64> {{"
+    )
+    .unwrap();
+    writeln!(file, "        match (self, to) {{").unwrap();
+    for region in &registry.regions {
+        if region.coordinates().is_some() {
+            writeln!(
+                file,
+                "            (Region::{0}, Region::{0}) => Some(0.0),",
+                region.name
+            )
+            .unwrap();
+        }
     }
+    for region in &registry.regions {
+        for (to, rtt) in &region.rtt_ms {
+            writeln!(
+                file,
+                "            (Region::{0}, Region::{1}) | (Region::{1}, Region::{0}) => Some({2:?}),",
+                region.name, to, rtt
+            )
+            .unwrap();
+        }
+    }
+    writeln!(file, "            _ => None,").unwrap();
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Ordered backup regions for when this one is down."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn failover(&self) -> &'static [Region] {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        let chain: Vec<String> = region
+            .failover
+            .iter()
+            .map(|r| format!("Region::{}", r))
+            .collect();
+        writeln!(
+            file,
+            "            Region::{} => &[{}],",
+            region.name,
+            chain.join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Canonical infrastructure code (e.g. \"fra1\"); None for Unknown."
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "    pub fn short_code(&self) -> Option<&'static str> {{"
+    )
+    .unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for region in &registry.regions {
+        let code = match &region.short_code {
+            Some(code) => format!("Some({:?})", code),
+            None => "None".to_string(),
+        };
+        writeln!(file, "            Region::{} => {},", region.name, code).unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Resolve a region name, short code, alias or geo label (case-insensitive)."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn resolve(name: &str) -> Option<Region> {{").unwrap();
+    writeln!(file, "        match name.to_ascii_lowercase().as_str() {{").unwrap();
+    for region in &registry.regions {
+        let patterns: Vec<String> = region
+            .input_names()
+            .iter()
+            .map(|n| format!("{:?}", n))
+            .collect();
+        writeln!(
+            file,
+            "            {} => Some(Region::{}),",
+            patterns.join(" | "),
+            region.name
+        )
+        .unwrap();
+    }
+    writeln!(file, "            _ => None,").unwrap();
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(
+        file,
+        "    /// Region name as used in regions.json and responses."
+    )
+    .unwrap();
+    writeln!(file, "    pub fn name(&self) -> &'static str {{").unwrap();
+    writeln!(file, "        match self {{").unwrap();
+    for name in &names {
+        writeln!(file, "            Region::{} => {:?},", name, name).unwrap();
+    }
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file, "}}").unwrap();
+    writeln!(file).unwrap();
+
+    writeln!(file, "impl From<u8> for Region {{").unwrap();
+    writeln!(file, "    fn from(value: u8) -> Self {{").unwrap();
+    writeln!(file, "        match value {{").unwrap();
+    for (code, name) in names.iter().enumerate() {
+        writeln!(file, "            {} => Region::{},", code, name).unwrap();
+    }
+    writeln!(file, "            _ => Region::DEFAULT,").unwrap();
+    writeln!(file, "        }}").unwrap();
+    writeln!(file, "    }}").unwrap();
+    writeln!(file, "}}").unwrap();
 }
 
-fn generate_validator_to_region_phf(geo_map: &HashMap<String, String>, out_path: &Path) {
+fn generate_validator_to_region_phf(
+    registry: &RegionRegistry,
+    geo_map: &HashMap<String, GeoEntry>,
+    geo_hash: &str,
+    out_path: &Path,
+) {
     let mut entries = Vec::new();
     let mut valid_entries = 0;
     let mut skipped_entries = 0;
 
-    for (pubkey_b58, region) in geo_map {
-        // Decode base58 pubkey to bytes
-        let pubkey_bytes = match bs58::decode(pubkey_b58).into_vec() {
-            Ok(bytes) if bytes.len() == 32 => bytes,
-            Ok(bytes) => {
-                eprintln!(
-                    "Warning: skipping pubkey with invalid length: {} (got {})",
-                    &pubkey_b58[..8.min(pubkey_b58.len())],
-                    bytes.len()
-                );
-                skipped_entries += 1;
-                continue;
-            }
+    for (pubkey_b58, entry) in geo_map {
+        // Decode base58 pubkey into a [u8; 32] literal
+        let key_literal = match pubkey_literal(pubkey_b58) {
+            Ok(literal) => literal,
             Err(e) => {
                 eprintln!(
-                    "Warning: failed to decode pubkey {}: {}",
+                    "Warning: skipping {}: {}",
                     &pubkey_b58[..8.min(pubkey_b58.len())],
                     e
                 );
@@ -87,34 +817,69 @@ fn generate_validator_to_region_phf(geo_map: &HashMap<String, String>, out_path:
             }
         };
 
-        // Format key as [u8; 32] literal
-        let key_literal = format!(
-            "[{}]",
-            pubkey_bytes
-                .iter()
-                .map(|b| format!("0x{:02x}", b))
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
+        // Region as u8; names missing from the registry map to Unknown
+        let region = entry.region();
+        let region_code = registry.code(region).unwrap_or_else(|| {
+            eprintln!("Warning: unknown region {:?}, mapping to Unknown", region);
+            registry.code("Unknown").expect("validated")
+        });
 
-        // Region as u8
-        let region_code = region_to_u8(region);
+        // Country as a 2-byte literal
+        let country_literal = match entry.country() {
+            Some(country) => format!("Some(*b{:?})", country),
+            None => "None".to_string(),
+        };
+
+        // Coordinates as f32 (~1 m precision, far finer than IP geolocation)
+        let coordinates_literal = match entry.coordinates() {
+            Some((lat, lon)) => format!("Some(({:?}f32, {:?}f32))", lat as f32, lon as f32),
+            None => "None".to_string(),
+        };
+
+        let value_literal = format!(
+            "GeoRecord {{ region: {}, country: {}, coordinates: {}, city: {:?}, asn: {:?}, provider: {:?} }}",
+            region_code,
+            country_literal,
+            coordinates_literal,
+            entry.city(),
+            entry.asn(),
+            entry.provider()
+        );
 
-        entries.push((key_literal, region_code));
+        entries.push((key_literal, value_literal));
         valid_entries += 1;
     }
 
     let phf_path = out_path.join("phf_geo.rs");
     let mut file = fs::File::create(&phf_path).expect("Failed to create phf_geo.rs");
 
-    writeln!(file, "/// Auto-generated PHF map: validator pubkey -> region").unwrap();
+    writeln!(
+        file,
+        "/// Auto-generated PHF map: validator pubkey -> GeoRecord"
+    )
+    .unwrap();
     writeln!(file, "/// Generated by build.rs from data/leader_geo.json").unwrap();
-    writeln!(file, "/// Valid entries: {}, Skipped: {}", valid_entries, skipped_entries).unwrap();
+    writeln!(
+        file,
+        "/// Valid entries: {}, Skipped: {}",
+        valid_entries, skipped_entries
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "pub const GEO_DATA_SHA256: Option<&str> = Some(\"{}\");",
+        geo_hash
+    )
+    .unwrap();
 
     // Use phf_map! macro directly for [u8; 32] keys
-    writeln!(file, "pub static VALIDATOR_TO_REGION: phf::Map<[u8; 32], u8> = phf::phf_map! {{").unwrap();
+    writeln!(
+        file,
+        "pub static VALIDATOR_TO_REGION: phf::Map<[u8; 32], GeoRecord> = phf::phf_map! {{"
+    )
+    .unwrap();
     for (key, value) in &entries {
-        writeln!(file, "    {} => {}u8,", key, value).unwrap();
+        writeln!(file, "    {} => {},", key, value).unwrap();
     }
     writeln!(file, "}};").unwrap();
 
@@ -124,19 +889,230 @@ fn generate_validator_to_region_phf(geo_map: &HashMap<String, String>, out_path:
     );
 }
 
-/// Create stub files for initial compilation without data files.
-fn create_stub_files() {
-    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
-    let out_path = Path::new(&out_dir);
+/// Generate the identity PHF maps: validator pubkey -> (name, stake in whole
+/// SOL), and vote account <-> identity (an identity maps to its first, most
+/// staked vote account). The source file is optional; without it the maps
+/// are empty.
+fn generate_validator_info_phf(info_path: &str, out_path: &Path) {
+    let info_map: HashMap<String, ValidatorInfo> = match fs::read_to_string(info_path) {
+        Ok(json) => serde_json::from_str(&json).expect("Failed to parse validator_info.json"),
+        Err(_) => HashMap::new(),
+    };
+
+    let phf_path = out_path.join("phf_identity.rs");
+    let mut file = fs::File::create(&phf_path).expect("Failed to create phf_identity.rs");
+    writeln!(
+        file,
+        "/// Auto-generated PHF map: validator pubkey -> (name, stake in SOL)"
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "/// Generated by build.rs from data/validator_info.json"
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "pub static VALIDATOR_INFO: phf::Map<[u8; 32], (Option<&'static str>, u64)> = phf::phf_map! {{"
+    )
+    .unwrap();
+    let mut count = 0;
+    let mut vote_to_identity = Vec::new();
+    let mut identity_to_vote = Vec::new();
+    let mut seen_votes = std::collections::HashSet::new();
+    for (pubkey_b58, info) in &info_map {
+        let Ok(key) = pubkey_literal(pubkey_b58) else {
+            continue;
+        };
+        if !info.stake_sol.is_finite() || info.stake_sol < 0.0 {
+            continue;
+        }
+        let name = info.name.as_deref().filter(|n| !n.is_empty());
+        writeln!(
+            file,
+            "    {} => ({:?}, {}),",
+            key,
+            name,
+            info.stake_sol.round() as u64
+        )
+        .unwrap();
+        count += 1;
+
+        let votes: Vec<String> = info
+            .vote_accounts
+            .iter()
+            .filter(|vote| seen_votes.insert(vote.as_str()))
+            .filter_map(|vote| pubkey_literal(vote).ok())
+            .collect();
+        if let Some(first) = votes.first() {
+            identity_to_vote.push(format!("    {} => {},", key, first));
+        }
+        vote_to_identity.extend(votes.iter().map(|vote| format!("    {} => {},", vote, key)));
+    }
+    writeln!(file, "}};").unwrap();
 
+    writeln!(file, "/// Vote account -> identity pubkey").unwrap();
+    writeln!(
+        file,
+        "pub static VOTE_TO_IDENTITY: phf::Map<[u8; 32], [u8; 32]> = phf::phf_map! {{"
+    )
+    .unwrap();
+    for line in &vote_to_identity {
+        writeln!(file, "{}", line).unwrap();
+    }
+    writeln!(file, "}};").unwrap();
+    writeln!(file, "/// Identity pubkey -> its most staked vote account").unwrap();
+    writeln!(
+        file,
+        "pub static IDENTITY_TO_VOTE: phf::Map<[u8; 32], [u8; 32]> = phf::phf_map! {{"
+    )
+    .unwrap();
+    for line in &identity_to_vote {
+        writeln!(file, "{}", line).unwrap();
+    }
+    writeln!(file, "}};").unwrap();
+
+    if count > 0 {
+        println!(
+            "cargo:warning=Generated identity PHF map: {} validators",
+            count
+        );
+    }
+}
+
+/// Generate the geo data timestamp and staleness limit. The metadata file is
+/// optional unless `LEADER_ROUTING_MAX_DATA_AGE_HOURS` is set: a build that
+/// must refuse stale data has to know how old its data is.
+fn generate_geo_meta(meta_path: &str, cluster: Option<&str>, out_path: &Path) {
+    let generated_at = fs::read_to_string(meta_path).ok().map(|json| {
+        serde_json::from_str::<GeoMeta>(&json)
+            .expect("Failed to parse leader_geo_meta.json")
+            .generated_at
+    });
+    let max_age_hours = env::var("LEADER_ROUTING_MAX_DATA_AGE_HOURS")
+        .ok()
+        .map(|hours| {
+            hours
+                .trim()
+                .parse::<u64>()
+                .expect("LEADER_ROUTING_MAX_DATA_AGE_HOURS must be a whole number of hours")
+        });
+    assert!(
+        max_age_hours.is_none() || generated_at.is_some(),
+        "LEADER_ROUTING_MAX_DATA_AGE_HOURS needs {}; \
+         regenerate the geo data with scripts/precompute_geo.py",
+        meta_path
+    );
+
+    let meta_file = out_path.join("geo_meta.rs");
+    let mut file = fs::File::create(&meta_file).expect("Failed to create geo_meta.rs");
+    writeln!(
+        file,
+        "/// Generated by build.rs from data/leader_geo_meta.json"
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "pub const GEO_DATA_GENERATED_AT: Option<u64> = {:?};",
+        generated_at
+    )
+    .unwrap();
+    writeln!(file, "/// From LEADER_ROUTING_MAX_DATA_AGE_HOURS").unwrap();
+    writeln!(
+        file,
+        "pub const MAX_DATA_AGE_HOURS: Option<u64> = {:?};",
+        max_age_hours
+    )
+    .unwrap();
+    writeln!(
+        file,
+        "/// From LEADER_ROUTING_CLUSTER (None: the default data/ files)"
+    )
+    .unwrap();
+    writeln!(file, "pub const CLUSTER: Option<&str> = {:?};", cluster).unwrap();
+}
+
+/// Generate the TPU PHF map (validator pubkey -> TpuEndpoints). The source
+/// file is optional; without it the map is empty. Addresses that do not
+/// parse as `ip:port` are dropped.
+fn generate_tpu_phf(tpu_path: &str, out_path: &Path) {
+    let tpu_map: HashMap<String, TpuEntry> = match fs::read_to_string(tpu_path) {
+        Ok(json) => serde_json::from_str(&json).expect("Failed to parse leader_tpu.json"),
+        Err(_) => HashMap::new(),
+    };
+    let address = |addr: &Option<String>| {
+        addr.as_deref()
+            .filter(|a| a.parse::<std::net::SocketAddr>().is_ok())
+            .map(str::to_string)
+    };
+
+    let phf_path = out_path.join("phf_tpu.rs");
+    let mut file = fs::File::create(&phf_path).expect("Failed to create phf_tpu.rs");
+    writeln!(
+        file,
+        "/// Auto-generated PHF map: validator pubkey -> TPU endpoints"
+    )
+    .unwrap();
+    writeln!(file, "/// Generated by build.rs from data/leader_tpu.json").unwrap();
+    writeln!(
+        file,
+        "pub static VALIDATOR_TPU: phf::Map<[u8; 32], TpuEndpoints> = phf::phf_map! {{"
+    )
+    .unwrap();
+    let mut count = 0;
+    for (pubkey_b58, entry) in &tpu_map {
+        let Ok(key) = pubkey_literal(pubkey_b58) else {
+            continue;
+        };
+        let (tpu, tpu_quic, tpu_forwards) = (
+            address(&entry.tpu),
+            address(&entry.tpu_quic),
+            address(&entry.tpu_forwards),
+        );
+        if tpu.is_none() && tpu_quic.is_none() {
+            continue;
+        }
+        writeln!(
+            file,
+            "    {} => TpuEndpoints {{ tpu: {:?}, tpu_quic: {:?}, tpu_forwards: {:?} }},",
+            key, tpu, tpu_quic, tpu_forwards
+        )
+        .unwrap();
+        count += 1;
+    }
+    writeln!(file, "}};").unwrap();
+
+    if count > 0 {
+        println!("cargo:warning=Generated TPU PHF map: {} validators", count);
+    }
+}
+
+/// `[u8; 32]` literal for a base58 pubkey, or why it is not a pubkey.
+fn pubkey_literal(pubkey_b58: &str) -> Result<String, String> {
+    let bytes = bs58::decode(pubkey_b58)
+        .into_vec()
+        .map_err(|e| format!("failed to decode pubkey: {}", e))?;
+    if bytes.len() != 32 {
+        return Err(format!("pubkey with invalid length (got {})", bytes.len()));
+    }
+    let bytes: Vec<String> = bytes.iter().map(|b| format!("0x{:02x}", b)).collect();
+    Ok(format!("[{}]", bytes.join(", ")))
+}
+
+/// Create stub files for initial compilation without data files.
+fn create_stub_files(out_path: &Path) {
     // Stub PHF geo map (empty)
     let geo_path = out_path.join("phf_geo.rs");
     let mut geo_file = fs::File::create(&geo_path).expect("Failed to create phf_geo.rs");
-    writeln!(geo_file, "/// STUB: Run python scripts/precompute_geo.py to generate real data")
-        .expect("Failed to write");
     writeln!(
         geo_file,
-        "pub static VALIDATOR_TO_REGION: phf::Map<[u8; 32], u8> = phf::phf_map! {{}};"
+        "/// STUB: Run python scripts/precompute_geo.py to generate real data"
+    )
+    .expect("Failed to write");
+    writeln!(geo_file, "pub const GEO_DATA_SHA256: Option<&str> = None;").expect("Failed to write");
+    writeln!(
+        geo_file,
+        "pub static VALIDATOR_TO_REGION: phf::Map<[u8; 32], GeoRecord> = phf::phf_map! {{}};"
     )
     .expect("Failed to write");
 
@@ -0,0 +1,29 @@
+/* C ABI for leader_routing's geo lookup. Build with `--features ffi`. */
+#ifndef LEADER_ROUTING_H
+#define LEADER_ROUTING_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Region codes are positions in data/regions.json. */
+
+/* Region a 32-byte validator pubkey geolocates to; Unknown if null or not found. */
+uint8_t lr_get_region(const uint8_t *pubkey);
+
+/* Region the procedure routes the validator's traffic to. */
+uint8_t lr_get_serving_region(const uint8_t *pubkey);
+
+/* Region name for a code, or NULL for an unknown code. Free with lr_free_string. */
+char *lr_region_name(uint8_t code);
+
+/* Free a string returned by this library. NULL is ignored. */
+void lr_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LEADER_ROUTING_H */
@@ -0,0 +1,120 @@
+#!/usr/bin/env python3
+"""
+Fetch validator names and activated stake for leader routing output.
+
+Reads vote accounts (getVoteAccounts) for stake and the vote account <->
+identity mapping, and the on-chain validator info published with
+`solana validator-info` (Config program accounts) for names. Optional: without the output file, responses omit leader_name and
+leader_stake_sol.
+
+Usage:
+    python scripts/fetch_validator_info.py [RPC_URL] [OUTPUT_PATH]
+
+Arguments:
+    RPC_URL     - Solana RPC endpoint (default: mainnet)
+    OUTPUT_PATH - Output JSON file (default: data/validator_info.json)
+
+Outputs:
+    data/validator_info.json - Identity pubkey -> {"name", "stake_sol",
+                               "vote_accounts"} mapping (name is null when
+                               none was published; vote accounts highest
+                               stake first)
+"""
+
+import json
+import logging
+import os
+import sys
+from typing import Any, Dict, List, Optional, Tuple
+
+import requests
+
+logging.basicConfig(
+    level=logging.INFO,
+    format="%(asctime)s %(levelname)s: %(message)s",
+    datefmt="%H:%M:%S"
+)
+log = logging.getLogger(__name__)
+
+CONFIG_PROGRAM_ID = "Config1111111111111111111111111111111111111"
+LAMPORTS_PER_SOL = 1_000_000_000
+
+
+def rpc_call(rpc_url: str, method: str, params: list) -> Any:
+    """Call a Solana RPC method and return its result."""
+    resp = requests.post(
+        rpc_url,
+        json={"jsonrpc": "2.0", "id": 1, "method": method, "params": params},
+        timeout=60
+    )
+    resp.raise_for_status()
+    result = resp.json()
+    if "error" in result:
+        raise Exception(f"RPC error: {result['error']}")
+    return result["result"]
+
+
+def fetch_vote_accounts(rpc_url: str) -> Dict[str, List[Tuple[str, int]]]:
+    """(vote account, activated stake in lamports) per identity, highest stake first."""
+    accounts = rpc_call(rpc_url, "getVoteAccounts", [])
+    votes: Dict[str, List[Tuple[str, int]]] = {}
+    for account in accounts["current"] + accounts["delinquent"]:
+        votes.setdefault(account["nodePubkey"], []).append(
+            (account["votePubkey"], account["activatedStake"])
+        )
+    for accounts_of_identity in votes.values():
+        accounts_of_identity.sort(key=lambda vote: vote[1], reverse=True)
+    log.info(f"Found vote accounts for {len(votes)} validators")
+    return votes
+
+
+def fetch_names(rpc_url: str) -> Dict[str, str]:
+    """Published validator-info name per identity."""
+    accounts = rpc_call(
+        rpc_url, "getProgramAccounts", [CONFIG_PROGRAM_ID, {"encoding": "jsonParsed"}]
+    )
+    names: Dict[str, str] = {}
+    for account in accounts:
+        parsed = account["account"]["data"]
+        if not isinstance(parsed, dict) or parsed.get("parsed", {}).get("type") != "validatorInfo":
+            continue
+        info = parsed["parsed"]["info"]
+        # The identity is the signing key after the validator-info marker key
+        identity = next((k["pubkey"] for k in info.get("keys", []) if k.get("signer")), None)
+        name: Optional[str] = info.get("configData", {}).get("name")
+        if identity and name:
+            names[identity] = name.strip()
+    log.info(f"Found names for {len(names)} validators")
+    return names
+
+
+def main():
+    rpc_url = sys.argv[1] if len(sys.argv) > 1 else "https://api.mainnet-beta.solana.com"
+    output_path = sys.argv[2] if len(sys.argv) > 2 else "data/validator_info.json"
+
+    log.info(f"Using RPC: {rpc_url}")
+    log.info(f"Output: {output_path}")
+
+    votes = fetch_vote_accounts(rpc_url)
+    names = fetch_names(rpc_url)
+
+    info = {
+        identity: {
+            "name": names.get(identity),
+            "stake_sol": sum(stake for _, stake in accounts) / LAMPORTS_PER_SOL,
+            "vote_accounts": [vote for vote, _ in accounts],
+        }
+        for identity, accounts in votes.items()
+    }
+
+    output_dir = os.path.dirname(output_path)
+    if output_dir:
+        os.makedirs(output_dir, exist_ok=True)
+    with open(output_path, "w") as f:
+        json.dump(info, f)
+
+    log.info(f"Wrote {output_path} ({len(info)} validators)")
+
+
+if __name__ == "__main__":
+    main()
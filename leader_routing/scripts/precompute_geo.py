@@ -2,8 +2,8 @@
 """
 Precompute validator geographic locations for leader routing.
 
-Fetches validator IPs from Solana getClusterNodes, geolocates via ip-api.com,
-and maps to Zela regions.
+Fetches validator IPs from Solana getClusterNodes, geolocates via ip-api.com
+(or a local MaxMind GeoLite2 database), and maps to Zela regions.
 
 Usage:
     python scripts/precompute_geo.py [RPC_URL] [OUTPUT_PATH]
@@ -12,8 +12,21 @@ Arguments:
     RPC_URL     - Solana RPC endpoint (default: mainnet)
     OUTPUT_PATH - Output JSON file (default: data/leader_geo.json)
 
+Environment:
+    GEOLITE2_CITY_DB - Path to GeoLite2-City.mmdb. When set, IPs are looked up
+                       locally (pip install geoip2) instead of via ip-api.com:
+                       no rate limit, a full run takes seconds.
+    GEOLITE2_ASN_DB  - Optional path to GeoLite2-ASN.mmdb for asn/provider.
+
 Outputs:
-    data/leader_geo.json - Validator pubkey -> region mapping
+    data/leader_geo.json - Validator pubkey -> {"region", "country", "city",
+                           "lat", "lon", "asn", "provider"} mapping (country is
+                           an ISO 3166-1 alpha-2 code, lat/lon in degrees,
+                           provider the hosting org; null when not known)
+    data/leader_geo_meta.json - {"generated_at": unix seconds} for the geo data
+                                (staleness checks)
+    data/leader_tpu.json - Validator pubkey -> {"tpu", "tpu_quic", "tpu_forwards"}
+                           socket addresses from the same gossip snapshot
 """
 
 import json
@@ -22,7 +35,7 @@ import sys
 import time
 import logging
 import requests
-from typing import Dict, Optional
+from typing import Any, Dict, Optional
 
 logging.basicConfig(
     level=logging.INFO,
@@ -37,17 +50,22 @@ IP_API_DELAY = 60.0 / IP_API_RATE_LIMIT  # ~1.33 seconds between requests
 
 # Country code to Zela region mapping
 COUNTRY_TO_REGION: Dict[str, str] = {
-    # North/South America -> NewYork
-    "US": "NewYork", "CA": "NewYork", "MX": "NewYork",
+    # North/South America -> NewYork (Mexico -> LosAngeles). Countries are too
+    # coarse to split the US; the procedure routes west-coast validators to
+    # LosAngeles from their coordinates.
+    "US": "NewYork", "CA": "NewYork", "MX": "LosAngeles",
     "BR": "NewYork", "AR": "NewYork", "CL": "NewYork",
     "CO": "NewYork", "PE": "NewYork", "VE": "NewYork",
 
-    # Europe -> Frankfurt
-    "DE": "Frankfurt", "FR": "Frankfurt", "GB": "Frankfurt",
-    "NL": "Frankfurt", "BE": "Frankfurt", "CH": "Frankfurt",
+    # North-western Europe -> Amsterdam
+    "NL": "Amsterdam", "BE": "Amsterdam", "GB": "Amsterdam",
+    "IE": "Amsterdam",
+
+    # Rest of Europe -> Frankfurt
+    "DE": "Frankfurt", "FR": "Frankfurt", "CH": "Frankfurt",
     "AT": "Frankfurt", "PL": "Frankfurt", "CZ": "Frankfurt",
     "SE": "Frankfurt", "NO": "Frankfurt", "DK": "Frankfurt",
-    "FI": "Frankfurt", "IE": "Frankfurt", "PT": "Frankfurt",
+    "FI": "Frankfurt", "PT": "Frankfurt",
     "ES": "Frankfurt", "IT": "Frankfurt", "GR": "Frankfurt",
     "RO": "Frankfurt", "HU": "Frankfurt", "BG": "Frankfurt",
     "UA": "Frankfurt", "RU": "Frankfurt",  # Western Russia
@@ -59,12 +77,14 @@ COUNTRY_TO_REGION: Dict[str, str] = {
     "ZA": "Dubai", "NG": "Dubai", "KE": "Dubai",
     "IN": "Dubai", "PK": "Dubai", "BD": "Dubai",
 
-    # East/SE Asia, Oceania -> Tokyo
+    # East Asia -> Tokyo
     "JP": "Tokyo", "KR": "Tokyo", "CN": "Tokyo",
-    "HK": "Tokyo", "TW": "Tokyo", "SG": "Tokyo",
-    "MY": "Tokyo", "TH": "Tokyo", "VN": "Tokyo",
-    "PH": "Tokyo", "ID": "Tokyo", "AU": "Tokyo",
-    "NZ": "Tokyo",
+    "HK": "Tokyo", "TW": "Tokyo",
+
+    # South-East Asia, Oceania -> Singapore
+    "SG": "Singapore", "MY": "Singapore", "TH": "Singapore",
+    "VN": "Singapore", "PH": "Singapore", "ID": "Singapore",
+    "AU": "Singapore", "NZ": "Singapore",
 }
 
 
@@ -122,14 +142,55 @@ def extract_ip(gossip_addr: str) -> Optional[str]:
         return None
 
 
-# Cache for geolocation results (IP -> country code)
+# Geolocation result: country, city, lat, lon, asn, provider (None when unavailable)
+GeoResult = Dict[str, Any]
+NOT_LOCATED: GeoResult = {
+    "country": None, "city": None, "lat": None, "lon": None, "asn": None, "provider": None,
+}
+
+# Cache for geolocation results (IP -> GeoResult)
 # Note: In-memory cache is sufficient since this script runs once per epoch (~2-3 days).
 # For persistent caching across runs, consider saving _geo_cache to a JSON file.
-_geo_cache: Dict[str, Optional[str]] = {}
-
-
-def geolocate_ip(ip: str, retry_count: int = 0) -> Optional[str]:
-    """Geolocate IP using ip-api.com. Returns country code or None.
+_geo_cache: Dict[str, GeoResult] = {}
+
+
+class GeoLite2:
+    """Local lookups against MaxMind GeoLite2 City (and optionally ASN) databases."""
+
+    def __init__(self, city_db: str, asn_db: Optional[str]):
+        import geoip2.database  # Only needed for this source
+
+        self._city = geoip2.database.Reader(city_db)
+        self._asn = geoip2.database.Reader(asn_db) if asn_db else None
+
+    def geolocate(self, ip: str) -> GeoResult:
+        import geoip2.errors
+
+        result = dict(NOT_LOCATED)
+        try:
+            city = self._city.city(ip)
+            result.update(
+                country=city.country.iso_code,
+                city=city.city.name,
+                lat=city.location.latitude,
+                lon=city.location.longitude,
+            )
+        except (geoip2.errors.AddressNotFoundError, ValueError):
+            return result
+        if self._asn:
+            try:
+                asn = self._asn.asn(ip)
+                result.update(
+                    asn=asn.autonomous_system_number,
+                    provider=asn.autonomous_system_organization,
+                )
+            except (geoip2.errors.AddressNotFoundError, ValueError):
+                pass
+        return result
+
+
+def geolocate_ip(ip: str, retry_count: int = 0) -> GeoResult:
+    """Geolocate IP using ip-api.com. Returns a GeoResult.
 
     Results are cached to avoid redundant API calls.
     Handles rate limit responses from ip-api.com with max 3 retries.
@@ -142,7 +203,7 @@ def geolocate_ip(ip: str, retry_count: int = 0) -> Optional[str]:
 
     try:
         resp = requests.get(
-            f"http://ip-api.com/json/{ip}?fields=status,countryCode,message",
+            f"http://ip-api.com/json/{ip}?fields=status,countryCode,city,lat,lon,as,org,isp,message",
             timeout=10
         )
         resp.raise_for_status()
@@ -152,22 +213,37 @@ def geolocate_ip(ip: str, retry_count: int = 0) -> Optional[str]:
         if data.get("status") == "fail" and "rate limit" in data.get("message", "").lower():
             if retry_count >= MAX_RETRIES:
                 log.error(f"Max retries ({MAX_RETRIES}) exceeded for rate limiting")
-                _geo_cache[ip] = None
-                return None
+                _geo_cache[ip] = NOT_LOCATED
+                return NOT_LOCATED
             log.warning(f"Rate limited by ip-api.com, waiting 60 seconds... (retry {retry_count + 1}/{MAX_RETRIES})")
             time.sleep(60)
             return geolocate_ip(ip, retry_count + 1)
 
         if data.get("status") == "success":
-            country = data.get("countryCode")
-            _geo_cache[ip] = country
-            return country
-        _geo_cache[ip] = None
-        return None
+            result = {
+                "country": data.get("countryCode"),
+                "city": data.get("city") or None,
+                "lat": data.get("lat"),
+                "lon": data.get("lon"),
+                "asn": parse_asn(data.get("as")),
+                "provider": data.get("org") or data.get("isp") or None,
+            }
+            _geo_cache[ip] = result
+            return result
+        _geo_cache[ip] = NOT_LOCATED
+        return NOT_LOCATED
     except Exception as e:
         log.warning(f"Geolocation failed for {ip}: {e}")
-        _geo_cache[ip] = None
+        _geo_cache[ip] = NOT_LOCATED
+        return NOT_LOCATED
+
+
+def parse_asn(as_field: Optional[str]) -> Optional[int]:
+    """ASN from ip-api's "as" field ("AS16509 Amazon.com, Inc.")."""
+    if not as_field or not as_field.startswith("AS"):
         return None
+    number = as_field[2:].split(" ", 1)[0]
+    return int(number) if number.isdigit() else None
 
 
 def country_to_region(country_code: Optional[str]) -> str:
@@ -190,11 +266,18 @@ def main():
         log.error(f"Output directory not writable: {output_dir}")
         sys.exit(1)
 
+    # Local GeoLite2 databases replace ip-api.com when configured
+    geolite2: Optional[GeoLite2] = None
+    city_db = os.environ.get("GEOLITE2_CITY_DB")
+    if city_db:
+        geolite2 = GeoLite2(city_db, os.environ.get("GEOLITE2_ASN_DB"))
+        log.info(f"Geolocating with GeoLite2: {city_db}")
+
     # Fetch cluster nodes
     nodes = fetch_cluster_nodes(rpc_url)
 
     # Process each validator
-    geo_map: Dict[str, str] = {}
+    geo_map: Dict[str, Dict[str, Any]] = {}
     stats = {"success": 0, "failed": 0, "skipped": 0}
 
     for i, node in enumerate(nodes):
@@ -208,18 +291,20 @@ def main():
         ip = extract_ip(gossip)
         if not ip:
             log.debug(f"No valid IP for {pubkey[:8]}...")
-            geo_map[pubkey] = "Unknown"
+            geo_map[pubkey] = {"region": "Unknown", **NOT_LOCATED}
             stats["skipped"] += 1
             continue
 
-        # Rate limit for ip-api.com
-        if i > 0 and stats["success"] > 0:
-            time.sleep(IP_API_DELAY)
-
-        # Geolocate
-        country = geolocate_ip(ip)
+        # Geolocate (ip-api.com is rate limited)
+        if geolite2:
+            located = geolite2.geolocate(ip)
+        else:
+            if i > 0 and stats["success"] > 0:
+                time.sleep(IP_API_DELAY)
+            located = geolocate_ip(ip)
+        country = located["country"]
         region = country_to_region(country)
-        geo_map[pubkey] = region
+        geo_map[pubkey] = {"region": region, **located}
 
         if region != "Unknown":
             stats["success"] += 1
@@ -237,9 +322,14 @@ def main():
 
     # Region distribution
     region_counts: Dict[str, int] = {}
-    for region in geo_map.values():
-        region_counts[region] = region_counts.get(region, 0) + 1
+    country_counts: Dict[str, int] = {}
+    for entry in geo_map.values():
+        region_counts[entry["region"]] = region_counts.get(entry["region"], 0) + 1
+        country = entry["country"] or "??"
+        country_counts[country] = country_counts.get(country, 0) + 1
     log.info(f"Region distribution: {region_counts}")
+    top_countries = sorted(country_counts.items(), key=lambda kv: -kv[1])[:10]
+    log.info(f"Top countries: {dict(top_countries)}")
 
     # Ensure output directory exists
     output_dir = os.path.dirname(output_path)
@@ -253,6 +343,26 @@ def main():
     file_size = len(json.dumps(geo_map))
     log.info(f"Wrote {output_path} ({file_size / 1024:.1f} KB, {len(geo_map)} entries)")
 
+    meta_path = os.path.join(output_dir, "leader_geo_meta.json")
+    with open(meta_path, "w") as f:
+        json.dump({"generated_at": int(time.time())}, f)
+    log.info(f"Wrote {meta_path}")
+
+    # TPU endpoints from the same gossip snapshot, next to the geo data
+    tpu_map = {
+        node["pubkey"]: {
+            "tpu": node.get("tpu"),
+            "tpu_quic": node.get("tpuQuic"),
+            "tpu_forwards": node.get("tpuForwards"),
+        }
+        for node in nodes
+        if node.get("pubkey") and (node.get("tpu") or node.get("tpuQuic"))
+    }
+    tpu_path = os.path.join(output_dir, "leader_tpu.json")
+    with open(tpu_path, "w") as f:
+        json.dump(tpu_map, f)
+    log.info(f"Wrote {tpu_path} ({len(tpu_map)} entries)")
+
 
 if __name__ == "__main__":
     main()
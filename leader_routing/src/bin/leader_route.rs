@@ -0,0 +1,115 @@
+//! `leader-route`: run a routing query from the command line.
+//!
+//! ```text
+//! leader-route [--url URL] [--slot SLOT] [--mode MODE] [--json]
+//! ```
+//!
+//! Without `--mode` it prints the slot, leader, leader geo and chosen region
+//! for the current slot (or `--slot`). Other modes, and `--json`, print the
+//! procedure's JSON response. Build with `--features cli`.
+
+use std::process::ExitCode;
+
+use leader_routing::{Input, LeaderRouting, Output, Response, RpcClient};
+
+const DEFAULT_URL: &str = "https://api.mainnet-beta.solana.com";
+
+const USAGE: &str = "usage: leader-route [--url URL] [--slot SLOT] [--mode MODE] [--json]
+
+  --url URL    Solana RPC endpoint (default: https://api.mainnet-beta.solana.com)
+  --slot SLOT  Route this slot instead of the current one
+  --mode MODE  current, plan, slo_check, next_epoch, epoch_forecast,
+               leader_stats, validators or health
+  --json       Print the procedure's JSON response";
+
+/// Parsed command line.
+struct Args {
+    url: String,
+    input: serde_json::Value,
+    json: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut url = DEFAULT_URL.to_string();
+    let mut input = serde_json::Map::new();
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match arg.as_str() {
+            "--url" => url = value("--url")?,
+            "--slot" => {
+                let slot: u64 = value("--slot")?
+                    .parse()
+                    .map_err(|_| "--slot must be a slot number".to_string())?;
+                input.insert("slot".into(), slot.into());
+            }
+            "--mode" => {
+                input.insert("mode".into(), value("--mode")?.into());
+            }
+            "--json" => json = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            other => return Err(format!("unknown argument {}\n\n{}", other, USAGE)),
+        }
+    }
+    // Anything but the current mode only has a JSON form
+    json |= input.get("mode").is_some_and(|mode| mode != "current");
+    Ok(Args {
+        url,
+        input: input.into(),
+        json,
+    })
+}
+
+fn print_summary(output: &Output) {
+    let name = output
+        .leader_name
+        .map(|name| format!(" ({})", name))
+        .unwrap_or_default();
+    let country = output
+        .leader_country
+        .map(|c| format!(", {}", c))
+        .unwrap_or_default();
+    println!("slot    {}", output.slot);
+    println!("leader  {}{}", output.leader, name);
+    println!("geo     {}{}", output.leader_geo, country);
+    println!("region  {} ({})", output.closest_region, output.code);
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::from(2);
+        }
+    };
+    let input: Input = match serde_json::from_value(args.input) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("invalid input: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let client = RpcClient::new(args.url);
+    match LeaderRouting::handle(&client, input).await {
+        Ok(envelope) if args.json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&envelope).expect("response serializes")
+            );
+            ExitCode::SUCCESS
+        }
+        Ok(envelope) => {
+            if let Response::Current(output) = &envelope.data {
+                print_summary(output);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error {}: {}", err.code, err.message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Distance metrics for region selection.
+//!
+//! Region selection (see [`crate::region::select`]) ranks candidate regions
+//! by a [`DistanceMetric`]. Two metrics ship with the crate:
+//!
+//! - [`GreatCircle`]: haversine distance between region coordinates, in km
+//! - [`LatencyMatrix`]: measured inter-region RTT from `rtt_ms` in regions.json,
+//!   and for a leader's own location an RTT estimate from fiber distance
+//!
+//! The metric used by the procedure is [`DefaultMetric`]: great-circle unless
+//! the `latency-matrix` feature is enabled. Experiments with other metrics
+//! implement the trait and call [`crate::region::select_by`] directly.
+
+use crate::region::Region;
+
+/// Mean Earth radius used for great-circle distances.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle km per millisecond of round trip over fiber (light covers
+/// ~200 km/ms in glass, there and back).
+const KM_PER_RTT_MS: f64 = 100.0;
+
+/// Metric used by the procedure, chosen at build time.
+#[cfg(not(feature = "latency-matrix"))]
+pub type DefaultMetric = GreatCircle;
+
+/// Metric used by the procedure, chosen at build time.
+#[cfg(feature = "latency-matrix")]
+pub type DefaultMetric = LatencyMatrix;
+
+/// Cost of serving one region's traffic from another.
+pub trait DistanceMetric {
+    /// Distance from `from` to `to`, or None if the metric has no data for
+    /// the pair (such regions are not selected).
+    fn distance(&self, from: Region, to: Region) -> Option<f64>;
+
+    /// Distance from a point (lat, lon in degrees), such as a leader's own
+    /// location, to `to`.
+    fn distance_from(&self, point: (f64, f64), to: Region) -> Option<f64>;
+
+    /// Fixed cost of serving from any region, in the same unit as
+    /// [`distance`](Self::distance).
+    ///
+    /// Selection divides distance plus baseline by the region weight, so a
+    /// down-weighted region also sheds traffic from its own area.
+    fn baseline(&self) -> f64;
+
+    /// Unit of distances, reported alongside region scores.
+    fn unit(&self) -> &'static str;
+}
+
+/// Great-circle distance between region coordinates, in km.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreatCircle;
+
+impl DistanceMetric for GreatCircle {
+    fn distance(&self, from: Region, to: Region) -> Option<f64> {
+        Some(haversine_km(from.coordinates()?, to.coordinates()?))
+    }
+
+    fn distance_from(&self, point: (f64, f64), to: Region) -> Option<f64> {
+        Some(haversine_km(point, to.coordinates()?))
+    }
+
+    fn baseline(&self) -> f64 {
+        1000.0
+    }
+
+    fn unit(&self) -> &'static str {
+        "km"
+    }
+}
+
+/// Measured round-trip time between regions, in ms.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyMatrix;
+
+impl DistanceMetric for LatencyMatrix {
+    fn distance(&self, from: Region, to: Region) -> Option<f64> {
+        from.rtt_ms(to)
+    }
+
+    /// No measurements exist from arbitrary points; estimated from the
+    /// great-circle distance at fiber speed.
+    fn distance_from(&self, point: (f64, f64), to: Region) -> Option<f64> {
+        Some(haversine_km(point, to.coordinates()?) / KM_PER_RTT_MS)
+    }
+
+    fn baseline(&self) -> f64 {
+        // Roughly the RTT of 1000 km of fiber, matching GreatCircle
+        10.0
+    }
+
+    fn unit(&self) -> &'static str {
+        "ms"
+    }
+}
+
+/// Great-circle (haversine) distance between two (lat, lon) points in degrees.
+pub fn haversine_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversine_km() {
+        let frankfurt = Region::Frankfurt.coordinates().unwrap();
+        let new_york = Region::NewYork.coordinates().unwrap();
+        let d = haversine_km(frankfurt, new_york);
+        assert!((6100.0..6300.0).contains(&d), "{d}");
+        assert_eq!(haversine_km(frankfurt, frankfurt), 0.0);
+    }
+
+    #[test]
+    fn test_great_circle_unknown_has_no_distance() {
+        assert_eq!(GreatCircle.distance(Region::Unknown, Region::Tokyo), None);
+    }
+
+    #[test]
+    fn test_distance_from_point() {
+        let frankfurt = Region::Frankfurt.coordinates().unwrap();
+        assert_eq!(
+            GreatCircle.distance_from(frankfurt, Region::Frankfurt),
+            Some(0.0)
+        );
+        let km = GreatCircle
+            .distance_from(frankfurt, Region::NewYork)
+            .unwrap();
+        let ms = LatencyMatrix
+            .distance_from(frankfurt, Region::NewYork)
+            .unwrap();
+        assert!((ms - km / KM_PER_RTT_MS).abs() < 1e-9);
+        assert_eq!(
+            LatencyMatrix.distance_from(frankfurt, Region::Unknown),
+            None
+        );
+    }
+
+    #[test]
+    fn test_latency_matrix_is_symmetric() {
+        for a in Region::ALL {
+            for b in Region::ALL {
+                assert_eq!(LatencyMatrix.distance(a, b), LatencyMatrix.distance(b, a));
+            }
+        }
+        assert_eq!(
+            LatencyMatrix.distance(Region::Tokyo, Region::Tokyo),
+            Some(0.0)
+        );
+        assert_eq!(LatencyMatrix.distance(Region::Unknown, Region::Tokyo), None);
+    }
+}
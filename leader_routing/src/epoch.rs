@@ -0,0 +1,527 @@
+//! Next-epoch preview: where leaders will be once the epoch rotates.
+//!
+//! The leader schedule for epoch N+1 is fixed before epoch N ends, so
+//! `getLeaderSchedule` can answer for it ahead of time. The preview reports
+//! how the next epoch's slots split across serving regions and the first
+//! leader windows, so operators can scale regions before the rotation.
+//!
+//! [`forecast`] splits the rest of the current epoch the same way, and
+//! [`concentration`] ranks its validators by slot count.
+//! [`upcoming_slots`] answers the reverse question for the current epoch:
+//! when a given validator leads next. [`EpochProgress`] reports how far a slot
+//! is into its epoch.
+//!
+//! # Slot time
+//!
+//! [`slot_to_time_ms`] and [`time_to_slot`] convert between slot offsets and
+//! wall-clock time at the nominal 400ms per slot. Real slots run somewhat
+//! slower and vary, so results are estimates for short spans (TTLs, time to
+//! a boundary), not absolute timestamps.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+use zela_std::rpc_client::Pubkey;
+
+use crate::geo;
+use crate::identity;
+use crate::plan::{self, PlanWindow};
+use crate::region::Region;
+
+/// Slots of leader windows returned when the request does not say.
+pub const DEFAULT_PREVIEW_SLOTS: u64 = 400;
+
+/// Most slots of leader windows a preview may request.
+pub const MAX_PREVIEW_SLOTS: u64 = 2_000;
+
+/// Most upcoming slots listed by [`upcoming_slots`]; the count covers all.
+pub const MAX_LISTED_SLOTS: usize = 100;
+
+/// Validators ranked by [`concentration`] when the request does not say.
+pub const DEFAULT_TOP_N: usize = 20;
+
+/// Most validators [`concentration`] may rank.
+pub const MAX_TOP_N: usize = 500;
+
+/// Estimated milliseconds from a slot to the slot `slot` slots later.
+pub fn slot_to_time_ms(slot: u64) -> u64 {
+    slot.saturating_mul(plan::SLOT_DURATION_MS)
+}
+
+/// Slot offset reached `ms` milliseconds from now (whole slots elapsed).
+pub fn time_to_slot(ms: u64) -> u64 {
+    ms / plan::SLOT_DURATION_MS
+}
+
+/// Estimated slot at Unix time `at_ms`, given that `current_slot` is the slot
+/// at `now_ms`.
+pub fn slot_at_time(current_slot: u64, now_ms: u64, at_ms: u64) -> u64 {
+    if at_ms >= now_ms {
+        current_slot.saturating_add(time_to_slot(at_ms - now_ms))
+    } else {
+        current_slot.saturating_sub(time_to_slot(now_ms - at_ms))
+    }
+}
+
+/// Leader schedule of one epoch: validator identity to slot indexes
+/// relative to the epoch's first slot (the `getLeaderSchedule` shape).
+pub type LeaderSchedule = HashMap<String, Vec<usize>>;
+
+/// Preview of the epoch after the current one.
+#[derive(Serialize, Debug)]
+pub struct EpochPreview {
+    pub epoch: u64,
+    /// First slot of the epoch.
+    pub first_slot: u64,
+    pub slots_in_epoch: u64,
+    /// Slots per serving region over the whole epoch, in registry order.
+    pub regions: Vec<RegionShare>,
+    /// Slots whose leader could not be geolocated (counted under their
+    /// fallback region in `regions`).
+    pub unknown_slots: u64,
+    /// Leader windows for the first `preview_slots` slots of the epoch.
+    pub windows: Vec<PlanWindow>,
+}
+
+/// Region split of the current epoch's remaining slots.
+#[derive(Serialize, Debug)]
+pub struct EpochForecast {
+    pub epoch: u64,
+    /// First slot counted.
+    pub start_slot: u64,
+    /// Last slot of the epoch.
+    pub end_slot: u64,
+    /// Scheduled slots from `start_slot` through `end_slot`.
+    pub remaining_slots: u64,
+    /// Slots per serving region over the remaining slots, in registry order.
+    pub regions: Vec<RegionShare>,
+    /// Remaining slots whose leader could not be geolocated (counted under
+    /// their fallback region in `regions`).
+    pub unknown_slots: u64,
+}
+
+/// Validators holding the most leader slots of an epoch.
+#[derive(Serialize, Debug)]
+pub struct LeaderConcentration {
+    pub epoch: u64,
+    /// Slots the schedule assigns.
+    pub scheduled_slots: u64,
+    /// Validators with at least one slot.
+    pub validators: usize,
+    /// Top validators by slot count, most slots first.
+    pub top: Vec<LeaderShare>,
+}
+
+/// One validator's part of an epoch's leader slots.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LeaderShare {
+    pub validator: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'static str>,
+    /// Region the validator's slots route to.
+    pub region: Region,
+    pub slots: u64,
+    /// `slots` over all scheduled slots, 0.0-1.0.
+    pub share: f64,
+    /// Share of this validator and every one ranked above it.
+    pub cumulative_share: f64,
+}
+
+/// Share of an epoch's slots served by one region.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RegionShare {
+    pub region: Region,
+    pub slots: u64,
+    /// `slots` over all scheduled slots, 0.0-1.0.
+    pub share: f64,
+}
+
+/// A validator's remaining leader slots in the current epoch.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ValidatorSlots {
+    pub validator: String,
+    pub epoch: u64,
+    /// Leader slots from the current slot on, ascending, at most
+    /// [`MAX_LISTED_SLOTS`].
+    pub slots: Vec<u64>,
+    /// All leader slots from the current slot to the end of the epoch.
+    pub upcoming_slots: u64,
+    /// Region the validator's slots route to.
+    pub region: Region,
+}
+
+/// Remaining leader slots of `validator` in `epoch`, from `current_slot` on.
+/// A validator absent from the schedule has none.
+pub fn upcoming_slots(
+    epoch: u64,
+    first_slot: u64,
+    current_slot: u64,
+    schedule: &LeaderSchedule,
+    validator: &Pubkey,
+    fallback: Option<Region>,
+) -> ValidatorSlots {
+    let mut slots: Vec<u64> = schedule
+        .get(&validator.to_string())
+        .into_iter()
+        .flatten()
+        .map(|&index| first_slot + index as u64)
+        .filter(|&slot| slot >= current_slot)
+        .collect();
+    slots.sort_unstable();
+    let upcoming_slots = slots.len() as u64;
+    slots.truncate(MAX_LISTED_SLOTS);
+
+    ValidatorSlots {
+        validator: validator.to_string(),
+        epoch,
+        slots,
+        upcoming_slots,
+        region: geo::route(&validator.to_bytes(), fallback).0,
+    }
+}
+
+/// Position of a slot within its epoch.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EpochProgress {
+    pub epoch: u64,
+    /// Slot index within the epoch (0-based).
+    pub slot_index: u64,
+    pub slots_in_epoch: u64,
+    /// Share of the epoch's slots before this one, 0.0-100.0.
+    pub percent_complete: f64,
+    /// Estimated time to the next epoch boundary, at 400ms per slot.
+    pub ms_to_boundary: u64,
+}
+
+impl EpochProgress {
+    /// Progress of `slot`, given that `epoch` starts at `first_slot`.
+    ///
+    /// Slots outside that epoch are placed assuming every epoch has
+    /// `slots_in_epoch` slots, as on mainnet after warmup. None if
+    /// `slots_in_epoch` is 0 or `slot` falls before epoch 0.
+    pub fn at(slot: u64, epoch: u64, first_slot: u64, slots_in_epoch: u64) -> Option<Self> {
+        if slots_in_epoch == 0 {
+            return None;
+        }
+        let offset = i128::from(slot) - i128::from(first_slot);
+        let epoch =
+            u64::try_from(i128::from(epoch) + offset.div_euclid(slots_in_epoch.into())).ok()?;
+        let slot_index = offset.rem_euclid(slots_in_epoch.into()) as u64;
+        Some(Self {
+            epoch,
+            slot_index,
+            slots_in_epoch,
+            percent_complete: slot_index as f64 / slots_in_epoch as f64 * 100.0,
+            ms_to_boundary: slot_to_time_ms(slots_in_epoch - slot_index),
+        })
+    }
+}
+
+/// Build the preview of `epoch` from its leader schedule.
+///
+/// Identities that are not valid pubkeys are skipped. Windows stop at the
+/// first slot the schedule does not assign.
+pub fn preview(
+    epoch: u64,
+    first_slot: u64,
+    slots_in_epoch: u64,
+    schedule: &LeaderSchedule,
+    preview_slots: u64,
+    fallback: Option<Region>,
+) -> EpochPreview {
+    let mut slots_by_region: HashMap<Region, u64> = HashMap::new();
+    let mut unknown_slots = 0;
+    let mut first_leaders: Vec<Option<Pubkey>> = vec![None; preview_slots as usize];
+
+    for (identity, slots) in schedule {
+        let Ok(leader) = Pubkey::from_str(identity) else {
+            continue;
+        };
+        let bytes = leader.to_bytes();
+        let (region, _) = geo::route(&bytes, fallback);
+        *slots_by_region.entry(region).or_default() += slots.len() as u64;
+        if geo::get_region(&bytes) == Region::Unknown {
+            unknown_slots += slots.len() as u64;
+        }
+        for &index in slots {
+            if let Some(entry) = first_leaders.get_mut(index) {
+                *entry = Some(leader);
+            }
+        }
+    }
+
+    let leaders: Vec<Pubkey> = first_leaders
+        .into_iter()
+        .map_while(|leader| leader)
+        .collect();
+
+    EpochPreview {
+        epoch,
+        first_slot,
+        slots_in_epoch,
+        regions: shares(&slots_by_region),
+        unknown_slots,
+        windows: plan::windows(first_slot, &leaders, fallback),
+    }
+}
+
+/// Split the slots of `epoch` from `start_slot` to its end by serving region.
+///
+/// Identities that are not valid pubkeys are skipped.
+pub fn forecast(
+    epoch: u64,
+    first_slot: u64,
+    slots_in_epoch: u64,
+    start_slot: u64,
+    schedule: &LeaderSchedule,
+    fallback: Option<Region>,
+) -> EpochForecast {
+    let start_index = start_slot.saturating_sub(first_slot) as usize;
+    let mut slots_by_region: HashMap<Region, u64> = HashMap::new();
+    let mut unknown_slots = 0;
+
+    for (identity, slots) in schedule {
+        let Ok(leader) = Pubkey::from_str(identity) else {
+            continue;
+        };
+        let remaining = slots.iter().filter(|&&index| index >= start_index).count() as u64;
+        if remaining == 0 {
+            continue;
+        }
+        let bytes = leader.to_bytes();
+        let (region, _) = geo::route(&bytes, fallback);
+        *slots_by_region.entry(region).or_default() += remaining;
+        if geo::get_region(&bytes) == Region::Unknown {
+            unknown_slots += remaining;
+        }
+    }
+
+    EpochForecast {
+        epoch,
+        start_slot,
+        end_slot: first_slot + slots_in_epoch.saturating_sub(1),
+        remaining_slots: slots_by_region.values().sum(),
+        regions: shares(&slots_by_region),
+        unknown_slots,
+    }
+}
+
+/// The `top_n` validators of `epoch` by slot count, ties in pubkey order.
+///
+/// Identities that are not valid pubkeys are skipped.
+pub fn concentration(
+    epoch: u64,
+    schedule: &LeaderSchedule,
+    top_n: usize,
+    fallback: Option<Region>,
+) -> LeaderConcentration {
+    let mut leaders: Vec<(Pubkey, u64)> = schedule
+        .iter()
+        .filter(|(_, slots)| !slots.is_empty())
+        .filter_map(|(identity, slots)| {
+            Some((Pubkey::from_str(identity).ok()?, slots.len() as u64))
+        })
+        .collect();
+    leaders.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let scheduled_slots: u64 = leaders.iter().map(|(_, slots)| slots).sum();
+
+    let mut cumulative = 0;
+    let top = leaders
+        .iter()
+        .take(top_n)
+        .map(|(leader, slots)| {
+            cumulative += slots;
+            let bytes = leader.to_bytes();
+            LeaderShare {
+                validator: leader.to_string(),
+                name: identity::get_name(&bytes),
+                region: geo::route(&bytes, fallback).0,
+                slots: *slots,
+                share: *slots as f64 / scheduled_slots as f64,
+                cumulative_share: cumulative as f64 / scheduled_slots as f64,
+            }
+        })
+        .collect();
+
+    LeaderConcentration {
+        epoch,
+        scheduled_slots,
+        validators: leaders.len(),
+        top,
+    }
+}
+
+/// Each region's share of the counted slots, in registry order.
+fn shares(slots_by_region: &HashMap<Region, u64>) -> Vec<RegionShare> {
+    let total: u64 = slots_by_region.values().sum();
+    Region::ALL
+        .iter()
+        .filter_map(|region| {
+            let slots = *slots_by_region.get(region)?;
+            Some(RegionShare {
+                region: *region,
+                slots,
+                share: slots as f64 / total as f64,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_inverts_schedule() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let b = Pubkey::new_from_array([2; 32]);
+        let schedule = LeaderSchedule::from([
+            (a.to_string(), vec![0, 1, 2, 3, 8, 9, 10, 11]),
+            (b.to_string(), vec![4, 5, 6, 7]),
+            ("not-a-pubkey".to_string(), vec![12]),
+        ]);
+
+        let preview = preview(801, 1_000, 12, &schedule, 10, Some(Region::Tokyo));
+
+        let starts: Vec<(u64, String)> = preview
+            .windows
+            .iter()
+            .map(|w| (w.window_start_slot, w.leader.clone()))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                (1_000, a.to_string()),
+                (1_004, b.to_string()),
+                (1_008, a.to_string())
+            ]
+        );
+        // Test pubkeys are not in the geo map, so every slot is Unknown
+        assert_eq!(preview.unknown_slots, 12);
+        assert_eq!(
+            preview.regions,
+            vec![RegionShare {
+                region: Region::Tokyo,
+                slots: 12,
+                share: 1.0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_forecast_counts_remaining_slots() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let b = Pubkey::new_from_array([2; 32]);
+        let schedule = LeaderSchedule::from([
+            (a.to_string(), vec![0, 1, 2, 3, 8, 9, 10, 11]),
+            (b.to_string(), vec![4, 5, 6, 7]),
+        ]);
+
+        let forecast = forecast(801, 1_000, 12, 1_008, &schedule, Some(Region::Tokyo));
+        assert_eq!((forecast.start_slot, forecast.end_slot), (1_008, 1_011));
+        assert_eq!(forecast.remaining_slots, 4);
+        assert_eq!(forecast.unknown_slots, 4);
+        assert_eq!(
+            forecast.regions,
+            vec![RegionShare {
+                region: Region::Tokyo,
+                slots: 4,
+                share: 1.0
+            }]
+        );
+
+        let done = super::forecast(801, 1_000, 12, 1_012, &schedule, None);
+        assert_eq!(done.remaining_slots, 0);
+        assert!(done.regions.is_empty());
+    }
+
+    #[test]
+    fn test_concentration_ranks_by_slots() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let b = Pubkey::new_from_array([2; 32]);
+        let c = Pubkey::new_from_array([3; 32]);
+        let schedule = LeaderSchedule::from([
+            (a.to_string(), vec![0, 1, 2, 3]),
+            (b.to_string(), (4..16).collect()),
+            (c.to_string(), (16..20).collect()),
+            ("not-a-pubkey".to_string(), vec![20]),
+        ]);
+
+        let stats = concentration(9, &schedule, 2, Some(Region::Dubai));
+        assert_eq!((stats.scheduled_slots, stats.validators), (20, 3));
+        let ranked: Vec<(String, u64)> = stats
+            .top
+            .iter()
+            .map(|l| (l.validator.clone(), l.slots))
+            .collect();
+        assert_eq!(ranked, vec![(b.to_string(), 12), (a.to_string(), 4)]);
+        assert_eq!(stats.top[0].share, 0.6);
+        assert_eq!(stats.top[1].cumulative_share, 0.8);
+        assert_eq!(stats.top[1].region, Region::Dubai);
+    }
+
+    #[test]
+    fn test_upcoming_slots_skips_past_and_caps_list() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let schedule = LeaderSchedule::from([(a.to_string(), (0..400).rev().collect())]);
+
+        let slots = upcoming_slots(7, 1_000, 1_100, &schedule, &a, Some(Region::Dubai));
+        assert_eq!(slots.upcoming_slots, 300);
+        assert_eq!(slots.slots.len(), MAX_LISTED_SLOTS);
+        assert_eq!(slots.slots[0], 1_100);
+        assert_eq!(slots.region, Region::Dubai);
+
+        let absent = Pubkey::new_from_array([2; 32]);
+        let none = upcoming_slots(7, 1_000, 1_100, &schedule, &absent, None);
+        assert_eq!(none.upcoming_slots, 0);
+        assert!(none.slots.is_empty());
+    }
+
+    #[test]
+    fn test_slot_time_conversion() {
+        assert_eq!(slot_to_time_ms(0), 0);
+        assert_eq!(slot_to_time_ms(4), 1_600);
+        assert_eq!(slot_to_time_ms(u64::MAX), u64::MAX);
+        assert_eq!(time_to_slot(1_600), 4);
+        assert_eq!(time_to_slot(1_999), 4);
+        for slot in [0, 1, 432_000] {
+            assert_eq!(time_to_slot(slot_to_time_ms(slot)), slot);
+        }
+    }
+
+    #[test]
+    fn test_slot_at_time() {
+        let now = 1_700_000_000_000;
+        assert_eq!(slot_at_time(1_000, now, now), 1_000);
+        assert_eq!(slot_at_time(1_000, now, now + 4_000), 1_010);
+        assert_eq!(slot_at_time(1_000, now, now - 4_000), 990);
+        assert_eq!(slot_at_time(1_000, now, 0), 0);
+    }
+
+    #[test]
+    fn test_epoch_progress() {
+        let progress = EpochProgress::at(1_108, 7, 1_000, 432).unwrap();
+        assert_eq!(progress.epoch, 7);
+        assert_eq!(progress.slot_index, 108);
+        assert_eq!(progress.percent_complete, 25.0);
+        assert_eq!(progress.ms_to_boundary, 324 * 400);
+
+        // Outside the reference epoch
+        assert_eq!(EpochProgress::at(1_432, 7, 1_000, 432).unwrap().epoch, 8);
+        let earlier = EpochProgress::at(999, 7, 1_000, 432).unwrap();
+        assert_eq!((earlier.epoch, earlier.slot_index), (6, 431));
+        assert_eq!(EpochProgress::at(0, 0, 1_000, 432), None);
+        assert_eq!(EpochProgress::at(5, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn test_preview_windows_stop_at_gap() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let schedule = LeaderSchedule::from([(a.to_string(), vec![0, 1, 3])]);
+
+        let preview = preview(1, 0, 4, &schedule, 4, None);
+        assert_eq!(preview.windows.len(), 1);
+        assert_eq!(preview.regions[0].slots, 3);
+    }
+}
@@ -0,0 +1,98 @@
+//! C ABI for the geo lookup (feature `ffi`).
+//!
+//! The crate already builds as a cdylib; with `--features ffi` it also
+//! exports these symbols, declared in `include/leader_routing.h`. Region codes
+//! are positions in regions.json, the same u8 codes the geo PHF map stores.
+
+use std::ffi::{c_char, CString};
+
+use crate::{geo, region::Region};
+
+/// Region code a validator geolocates to (Unknown if not in the geo data).
+///
+/// # Safety
+/// `pubkey` must be null or point to 32 readable bytes. Null returns Unknown.
+#[no_mangle]
+pub unsafe extern "C" fn lr_get_region(pubkey: *const u8) -> u8 {
+    match read_pubkey(pubkey) {
+        Some(bytes) => geo::get_region(&bytes) as u8,
+        None => Region::Unknown as u8,
+    }
+}
+
+/// Region code the procedure would route a validator's traffic to, after the
+/// Unknown fallback and capacity-aware selection.
+///
+/// # Safety
+/// `pubkey` must be null or point to 32 readable bytes. Null is treated as
+/// an unknown validator.
+#[no_mangle]
+pub unsafe extern "C" fn lr_get_serving_region(pubkey: *const u8) -> u8 {
+    let region = match read_pubkey(pubkey) {
+        Some(bytes) => geo::route(&bytes, None).0,
+        None => Region::Unknown.route(None).0,
+    };
+    region as u8
+}
+
+/// Region name for a code as a newly allocated C string, or null for an
+/// unknown code. Release it with [`lr_free_string`].
+#[no_mangle]
+pub extern "C" fn lr_region_name(code: u8) -> *mut c_char {
+    match Region::ALL.get(code as usize) {
+        Some(region) => CString::new(region.name())
+            .map(CString::into_raw)
+            .unwrap_or(std::ptr::null_mut()),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+/// `s` must be null or a pointer returned by [`lr_region_name`] that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lr_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Copy 32 bytes from a caller pointer, or None for null.
+unsafe fn read_pubkey(pubkey: *const u8) -> Option<[u8; 32]> {
+    if pubkey.is_null() {
+        return None;
+    }
+    Some(std::ptr::read_unaligned(pubkey.cast::<[u8; 32]>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_name_round_trip() {
+        let name = lr_region_name(Region::Tokyo as u8);
+        let owned = unsafe { std::ffi::CStr::from_ptr(name) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { lr_free_string(name) };
+        assert_eq!(owned, "Tokyo");
+        assert!(lr_region_name(u8::MAX).is_null());
+    }
+
+    #[test]
+    fn test_lookup_unknown_and_null() {
+        let pubkey = [0u8; 32];
+        unsafe {
+            assert_eq!(lr_get_region(pubkey.as_ptr()), Region::Unknown as u8);
+            assert_eq!(lr_get_region(std::ptr::null()), Region::Unknown as u8);
+            assert_eq!(
+                lr_get_serving_region(pubkey.as_ptr()),
+                Region::Frankfurt as u8
+            );
+        }
+    }
+}
@@ -1,7 +1,15 @@
 //! Validator-to-region lookup using precomputed PHF map.
 //!
 //! Uses a compile-time PHF (Perfect Hash Function) map generated by build.rs
-//! from data/leader_geo.json. Provides O(1) lookup for validator -> region.
+//! from data/leader_geo.json. Provides O(1) lookup for validator -> region,
+//! and validator -> ISO country code, city, coordinates and network (ASN,
+//! hosting provider) where the geo data records them.
+//!
+//! # Computed preference
+//!
+//! When a validator has coordinates, [`preference`] picks the region nearest
+//! to them by great-circle distance instead of the region precomputed from its
+//! country, so routing follows the leader's actual position.
 //!
 //! # Fallback Behavior
 //!
@@ -12,10 +20,35 @@
 //! - It's a common hosting location for blockchain infrastructure
 //!
 //! Note: If validator geographic distribution changes significantly,
-//! the default region can be updated by modifying `Region::DEFAULT` in region.rs.
+//! the fallback can be updated via the Unknown entry's `routes_to` in data/regions.json,
+//! or per deployment with the `LEADER_ROUTING_FALLBACK_REGION` build variable.
 //! Monitor distribution via `precompute_geo.py` output logs which show region counts.
 
-use crate::region::Region;
+use serde::Serialize;
+use zela_std::rpc_client::Pubkey;
+
+use crate::region::{self, Fallback, Region};
+
+/// Validators per page of [`validator_page`] when the request does not say.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Most validators per page of [`validator_page`].
+pub const MAX_PAGE_SIZE: usize = 1_000;
+
+/// Geo data for one validator (the PHF map value).
+pub struct GeoRecord {
+    /// Region code (index in regions.json).
+    pub region: u8,
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: Option<[u8; 2]>,
+    /// Latitude and longitude in degrees.
+    pub coordinates: Option<(f32, f32)>,
+    pub city: Option<&'static str>,
+    /// Autonomous system number of the validator's IP.
+    pub asn: Option<u32>,
+    /// Hosting provider / datacenter operator.
+    pub provider: Option<&'static str>,
+}
 
 // Include the generated PHF map
 include!(concat!(env!("OUT_DIR"), "/phf_geo.rs"));
@@ -40,11 +73,175 @@ pub fn is_stub() -> bool {
 /// The region where the validator is located, or Unknown if not found.
 pub fn get_region(pubkey: &[u8; 32]) -> Region {
     match VALIDATOR_TO_REGION.get(pubkey) {
-        Some(&region_code) => Region::from(region_code),
+        Some(record) => Region::from(record.region),
         None => Region::Unknown,
     }
 }
 
+/// Get the region for a validator, or None if it could not be geolocated.
+///
+/// Unlike [`get_region`], validators missing from the geo data and those the
+/// data lists as Unknown both return None, so callers need not compare
+/// against `Region::Unknown`.
+pub fn get_region_checked(pubkey: &[u8; 32]) -> Option<Region> {
+    Some(get_region(pubkey)).filter(|region| *region != Region::Unknown)
+}
+
+/// Validators in the embedded geo data, per region.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Coverage {
+    /// Validators in the geo data, including those listed as Unknown.
+    pub total: usize,
+    /// Validators assigned a region other than Unknown.
+    pub located: usize,
+    /// Validator count per region, in code order (Unknown included).
+    pub regions: Vec<RegionCount>,
+}
+
+/// Number of validators the geo data assigns to one region.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct RegionCount {
+    pub region: Region,
+    pub validators: usize,
+}
+
+/// Per-region validator counts of the embedded geo data.
+///
+/// Walks the whole map, so call it once rather than per request.
+pub fn coverage() -> Coverage {
+    let mut counts = vec![0; Region::ALL.len()];
+    for record in VALIDATOR_TO_REGION.values() {
+        counts[Region::from(record.region) as usize] += 1;
+    }
+    let unknown = counts[Region::Unknown as usize];
+    Coverage {
+        total: VALIDATOR_TO_REGION.len(),
+        located: VALIDATOR_TO_REGION.len() - unknown,
+        regions: Region::ALL
+            .iter()
+            .zip(counts)
+            .map(|(&region, validators)| RegionCount { region, validators })
+            .collect(),
+    }
+}
+
+/// One page of the validators the geo data assigns to a region.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ValidatorPage {
+    pub region: Region,
+    /// Validators assigned to `region`.
+    pub total: usize,
+    /// Index of the first validator on this page.
+    pub offset: usize,
+    /// Identity pubkeys (base58), in a stable order across requests.
+    pub validators: Vec<String>,
+    /// Counts for every region, for comparison.
+    pub coverage: Coverage,
+}
+
+/// Identity pubkeys the geo data assigns to `region`, sorted by key bytes.
+pub fn validators(region: Region) -> Vec<[u8; 32]> {
+    let mut keys: Vec<[u8; 32]> = VALIDATOR_TO_REGION
+        .entries()
+        .filter(|(_, record)| Region::from(record.region) == region)
+        .map(|(key, _)| *key)
+        .collect();
+    keys.sort_unstable();
+    keys
+}
+
+/// Validators `offset..offset + limit` of `region`, for auditing the
+/// classification page by page.
+pub fn validator_page(region: Region, offset: usize, limit: usize) -> ValidatorPage {
+    let keys = validators(region);
+    ValidatorPage {
+        region,
+        total: keys.len(),
+        offset,
+        validators: keys
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|key| Pubkey::new_from_array(*key).to_string())
+            .collect(),
+        coverage: coverage(),
+    }
+}
+
+/// Get the ISO 3166-1 alpha-2 country code a validator geolocated to.
+///
+/// Returns None for unknown validators and for geo data generated before
+/// `precompute_geo.py` recorded countries.
+pub fn get_country(pubkey: &[u8; 32]) -> Option<&'static str> {
+    let country = VALIDATOR_TO_REGION.get(pubkey)?.country.as_ref()?;
+    std::str::from_utf8(country).ok()
+}
+
+/// Get the latitude and longitude (degrees) a validator geolocated to.
+///
+/// Returns None for unknown validators and for geo data generated before
+/// `precompute_geo.py` recorded coordinates.
+pub fn get_coordinates(pubkey: &[u8; 32]) -> Option<(f64, f64)> {
+    let (lat, lon) = VALIDATOR_TO_REGION.get(pubkey)?.coordinates?;
+    Some((f64::from(lat), f64::from(lon)))
+}
+
+/// Get the city a validator geolocated to, if the geo data records it.
+pub fn get_city(pubkey: &[u8; 32]) -> Option<&'static str> {
+    VALIDATOR_TO_REGION.get(pubkey)?.city
+}
+
+/// Network a validator's IP belongs to, if the geo data records it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Network {
+    /// Autonomous system number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    /// Hosting provider / datacenter operator (e.g. "Hetzner Online GmbH").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<&'static str>,
+}
+
+/// Get the ASN and hosting provider of a validator's IP.
+///
+/// Returns None when the geo data records neither (unknown validators and
+/// data generated before `precompute_geo.py` recorded networks).
+pub fn get_network(pubkey: &[u8; 32]) -> Option<Network> {
+    let record = VALIDATOR_TO_REGION.get(pubkey)?;
+    let network = Network {
+        asn: record.asn,
+        provider: record.provider,
+    };
+    (network.asn.is_some() || network.provider.is_some()).then_some(network)
+}
+
+/// Whether the geo data places the validator, by region or coordinates.
+///
+/// Validators that are not located route to the Unknown fallback.
+pub fn is_located(pubkey: &[u8; 32]) -> bool {
+    get_region(pubkey) != Region::Unknown || get_coordinates(pubkey).is_some()
+}
+
+/// Preferred region for a validator's traffic, before capacity-aware
+/// selection.
+///
+/// The region nearest to the validator's coordinates when known, otherwise
+/// its precomputed region (see [`Region::preference`] for the Unknown
+/// fallback). Also returns which fallback rule was applied, if any.
+pub fn preference(pubkey: &[u8; 32], fallback: Option<Region>) -> (Region, Option<Fallback>) {
+    match get_coordinates(pubkey).and_then(|(lat, lon)| region::closest(lat, lon)) {
+        Some(closest) => (closest, None),
+        None => get_region(pubkey).preference(fallback),
+    }
+}
+
+/// Serving region for a validator's traffic: [`preference`] followed by
+/// capacity-aware [`region::select`].
+pub fn route(pubkey: &[u8; 32], fallback: Option<Region>) -> (Region, Option<Fallback>) {
+    let (preferred, applied) = preference(pubkey, fallback);
+    (region::select(preferred), applied)
+}
+
 /// Get the geographic label for a validator.
 ///
 /// Returns a human-readable location string like "Europe/Frankfurt".
@@ -53,6 +250,96 @@ pub fn get_geo_label(pubkey: &[u8; 32]) -> &'static str {
     get_region(pubkey).geo_label()
 }
 
+/// SHA-256 of the `leader_geo.json` this binary was built from.
+///
+/// Returns None when using stub data.
+pub fn data_hash() -> Option<&'static str> {
+    GEO_DATA_SHA256
+}
+
+// Include the generated data timestamp and staleness limit
+include!(concat!(env!("OUT_DIR"), "/geo_meta.rs"));
+
+/// Cluster the embedded validator data belongs to, when the build selected
+/// one with `LEADER_ROUTING_CLUSTER` (otherwise the default data/ files,
+/// normally mainnet).
+pub fn cluster() -> Option<&'static str> {
+    CLUSTER
+}
+
+/// Unix time (seconds) the embedded geo data was generated, if recorded.
+pub fn generated_at() -> Option<u64> {
+    GEO_DATA_GENERATED_AT
+}
+
+/// Age limit of the geo data set with `LEADER_ROUTING_MAX_DATA_AGE_HOURS`,
+/// in hours.
+pub fn max_data_age_hours() -> Option<u64> {
+    MAX_DATA_AGE_HOURS
+}
+
+/// Whether the geo data is past the configured age limit at Unix time
+/// `now_secs`. Always false without a limit.
+pub fn is_stale(now_secs: u64) -> bool {
+    match (MAX_DATA_AGE_HOURS, GEO_DATA_GENERATED_AT) {
+        (Some(hours), Some(generated_at)) => {
+            now_secs.saturating_sub(generated_at) > hours.saturating_mul(3600)
+        }
+        _ => false,
+    }
+}
+
+/// Freshness and size of the embedded geo data, for deciding when to
+/// redeploy.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DataHealth {
+    /// `LEADER_ROUTING_CLUSTER` of this build, if set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<&'static str>,
+    /// Validators in the geo map.
+    pub validators: usize,
+    /// Built without leader_geo.json: every leader routes to the fallback.
+    pub stub_data: bool,
+    /// SHA-256 of the embedded leader_geo.json.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_hash: Option<&'static str>,
+    /// Unix time (seconds) the geo data was generated, if recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<u64>,
+    /// Hours since `generated_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_hours: Option<u64>,
+    /// `LEADER_ROUTING_MAX_DATA_AGE_HOURS` of this build.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_hours: Option<u64>,
+    /// Hours until requests are refused as stale (0 once they are).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hours_until_stale: Option<u64>,
+    /// Stub data, or data past its age limit.
+    pub redeploy_due: bool,
+}
+
+/// Health of the embedded geo data at Unix time `now_secs`.
+pub fn health(now_secs: u64) -> DataHealth {
+    let age_secs = GEO_DATA_GENERATED_AT.map(|generated_at| now_secs.saturating_sub(generated_at));
+    let hours_until_stale = MAX_DATA_AGE_HOURS
+        .zip(age_secs)
+        .map(|(max_hours, age_secs)| {
+            max_hours.saturating_mul(3600).saturating_sub(age_secs) / 3600
+        });
+    DataHealth {
+        cluster: cluster(),
+        validators: validator_count(),
+        stub_data: is_stub(),
+        data_hash: data_hash(),
+        generated_at: GEO_DATA_GENERATED_AT,
+        age_hours: age_secs.map(|secs| secs / 3600),
+        max_age_hours: MAX_DATA_AGE_HOURS,
+        hours_until_stale,
+        redeploy_due: is_stub() || is_stale(now_secs),
+    }
+}
+
 /// Number of validators with known geo data.
 ///
 /// Returns 0 when using stub data (no leader_geo.json).
@@ -71,6 +358,69 @@ mod tests {
         assert_eq!(get_region(&pubkey), Region::Unknown);
     }
 
+    #[test]
+    fn test_unknown_pubkey_has_no_country() {
+        assert_eq!(get_country(&[0u8; 32]), None);
+        assert_eq!(get_city(&[0u8; 32]), None);
+        assert_eq!(get_network(&[0u8; 32]), None);
+    }
+
+    #[test]
+    fn test_unknown_pubkey_routes_to_fallback() {
+        assert_eq!(get_coordinates(&[0u8; 32]), None);
+        assert_eq!(
+            preference(&[0u8; 32], Some(Region::Tokyo)),
+            (Region::Tokyo, Some(Fallback::Input))
+        );
+        assert_eq!(route(&[0u8; 32], None).1, Some(Fallback::Registry));
+    }
+
+    #[test]
+    fn test_get_region_checked() {
+        assert_eq!(get_region_checked(&[0u8; 32]), None);
+        if let Some((key, record)) = VALIDATOR_TO_REGION.entries().next() {
+            let region = Region::from(record.region);
+            let expected = (region != Region::Unknown).then_some(region);
+            assert_eq!(get_region_checked(key), expected);
+        }
+    }
+
+    #[test]
+    fn test_coverage_counts_every_validator() {
+        let coverage = coverage();
+        assert_eq!(coverage.total, validator_count());
+        assert_eq!(coverage.regions.len(), Region::ALL.len());
+        let sum: usize = coverage.regions.iter().map(|c| c.validators).sum();
+        assert_eq!(sum, coverage.total);
+        let unknown = coverage.regions[Region::Unknown as usize].validators;
+        assert_eq!(coverage.located, coverage.total - unknown);
+    }
+
+    #[test]
+    fn test_validator_page_matches_coverage() {
+        let coverage = coverage();
+        for count in &coverage.regions {
+            let keys = validators(count.region);
+            assert_eq!(keys.len(), count.validators);
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+
+        let region = coverage
+            .regions
+            .iter()
+            .max_by_key(|c| c.validators)
+            .unwrap();
+        let page = validator_page(region.region, 1, 2);
+        assert_eq!(page.total, region.validators);
+        assert_eq!(
+            page.validators.len(),
+            region.validators.saturating_sub(1).min(2)
+        );
+        assert!(validator_page(region.region, usize::MAX, 10)
+            .validators
+            .is_empty());
+    }
+
     #[test]
     fn test_unknown_geo_label() {
         // Unknown validators have UNKNOWN geo label
@@ -123,6 +473,70 @@ mod tests {
         assert_eq!(Region::from(255u8), Region::Frankfurt);
     }
 
+    #[test]
+    fn test_data_hash_matches_stub_state() {
+        // Stub builds have no source file to hash
+        match data_hash() {
+            Some(hash) => assert_eq!(hash.len(), 64),
+            None => assert!(is_stub()),
+        }
+    }
+
+    #[test]
+    fn test_lookup_matches_source_data() {
+        // Every validator in leader_geo.json resolves to the region it lists
+        let data_dir = match cluster() {
+            Some(cluster) => format!("data/{}", cluster),
+            None => "data".to_string(),
+        };
+        let path = format!(
+            "{}/{}/leader_geo.json",
+            env!("CARGO_MANIFEST_DIR"),
+            data_dir
+        );
+        let Ok(json) = std::fs::read_to_string(path) else {
+            assert!(is_stub());
+            return;
+        };
+        let geo_map: std::collections::HashMap<String, serde_json::Value> =
+            serde_json::from_str(&json).unwrap();
+
+        let mut checked = 0;
+        for (pubkey_b58, entry) in &geo_map {
+            let region = entry.as_str().or_else(|| entry["region"].as_str()).unwrap();
+            let Ok(pubkey) = bs58::decode(pubkey_b58).into_vec() else {
+                continue;
+            };
+            let Ok(pubkey) = <[u8; 32]>::try_from(pubkey) else {
+                continue;
+            };
+            let expected = Region::resolve(region).unwrap_or(Region::Unknown);
+            assert_eq!(get_region(&pubkey), expected, "{}", pubkey_b58);
+            checked += 1;
+        }
+        assert_eq!(checked, validator_count());
+    }
+
+    #[test]
+    fn test_staleness_needs_a_limit() {
+        if max_data_age_hours().is_none() {
+            assert!(!is_stale(u64::MAX));
+        } else {
+            assert!(!is_stale(generated_at().unwrap()));
+            assert!(is_stale(u64::MAX));
+        }
+    }
+
+    #[test]
+    fn test_health_reports_embedded_data() {
+        let health = health(u64::MAX);
+        assert_eq!(health.validators, validator_count());
+        assert_eq!(health.stub_data, is_stub());
+        assert_eq!(health.data_hash, data_hash());
+        assert_eq!(health.generated_at.is_some(), health.age_hours.is_some());
+        assert_eq!(health.redeploy_due, is_stub() || is_stale(u64::MAX));
+    }
+
     #[test]
     fn test_validator_count() {
         // In stub mode, count is 0
@@ -0,0 +1,53 @@
+//! Validator identity metadata: published name, activated stake and vote
+//! accounts.
+//!
+//! Generated by build.rs from the optional data/validator_info.json (see
+//! `scripts/fetch_validator_info.py`). Without that file every lookup returns
+//! None and [`resolve_identity`] returns its input. Purely informational:
+//! routing never depends on it.
+
+// Include the generated PHF map
+include!(concat!(env!("OUT_DIR"), "/phf_identity.rs"));
+
+/// Name the validator published with `solana validator-info`, if any.
+pub fn get_name(pubkey: &[u8; 32]) -> Option<&'static str> {
+    VALIDATOR_INFO.get(pubkey)?.0
+}
+
+/// Activated stake across the validator's vote accounts, in whole SOL.
+pub fn get_stake_sol(pubkey: &[u8; 32]) -> Option<u64> {
+    VALIDATOR_INFO.get(pubkey).map(|&(_, stake)| stake)
+}
+
+/// Identity pubkey for `pubkey`: the identity voting with it if `pubkey` is
+/// a known vote account, otherwise `pubkey` itself.
+pub fn resolve_identity(pubkey: &[u8; 32]) -> [u8; 32] {
+    VOTE_TO_IDENTITY.get(pubkey).copied().unwrap_or(*pubkey)
+}
+
+/// The identity's vote account (the most staked one if it has several).
+pub fn get_vote_account(identity: &[u8; 32]) -> Option<[u8; 32]> {
+    IDENTITY_TO_VOTE.get(identity).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_validator_has_no_identity() {
+        assert_eq!(get_name(&[0u8; 32]), None);
+        assert_eq!(get_stake_sol(&[0u8; 32]), None);
+        assert_eq!(get_vote_account(&[0u8; 32]), None);
+        assert_eq!(resolve_identity(&[7u8; 32]), [7u8; 32]);
+    }
+
+    #[test]
+    fn test_vote_accounts_round_trip() {
+        for (vote, identity) in VOTE_TO_IDENTITY.entries() {
+            assert_eq!(resolve_identity(vote), *identity);
+            let primary = get_vote_account(identity).unwrap();
+            assert_eq!(VOTE_TO_IDENTITY.get(&primary), Some(identity));
+        }
+    }
+}
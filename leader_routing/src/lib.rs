@@ -5,30 +5,297 @@
 //!
 //! ## How it works
 //!
-//! 1. Get current slot from Solana RPC (source of truth)
+//! 1. Get current slot from Solana RPC (source of truth), or use `slot`
 //! 2. Get leader for that slot from Solana RPC
 //! 3. Look up leader's region (O(1) PHF lookup)
-//! 4. Return the closest region
+//! 4. Return the closest region (Unknown validators route to a fallback)
+//!
+//! With `"mode": "plan"` it instead returns a routing plan for the next
+//! `horizon_slots` slots (see [`plan`]), for clients that cache decisions.
+//! `"mode": "slo_check"` measures geo coverage of live leaders (see [`slo`]).
+//! `"mode": "next_epoch"` previews the next epoch's leaders, and
+//! `"mode": "epoch_forecast"` splits the rest of this one by region and
+//! `"mode": "leader_stats"` ranks its leaders by slot count (see [`epoch`]).
+//!
+//! ## Native use
+//!
+//! With the `native` feature the routing functions take solana-client's
+//! `RpcClient`, so a native sidecar can call [`route_now`], [`route_at`] or
+//! [`LeaderRouting::handle`] directly. The Zela entry point is left out of
+//! such builds.
 //!
 //! ## Performance
 //!
 //! - Geo lookup is O(1) via compiled PHF map
 //! - RPC calls are the latency bottleneck (~100-200ms)
+//! - Every stage logs its duration at debug level (see [`span`])
 
+pub mod distance;
+pub mod epoch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod geo;
+pub mod identity;
+pub mod plan;
 pub mod region;
+pub mod retry;
+pub mod slo;
+pub mod span;
+pub mod tpu;
+#[cfg(feature = "wasm-bindgen")]
+pub mod web;
 
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use common::{Envelope, Provenance};
 use serde::{Deserialize, Serialize};
-use zela_std::{zela_custom_procedure, rpc_client::RpcClient, CustomProcedure, RpcError};
+use solana_commitment_config::{CommitmentConfig, CommitmentLevel};
+use zela_std::{rpc_client::Pubkey, RpcError};
+#[cfg(not(feature = "native"))]
+use zela_std::{rpc_client::RpcClient, CustomProcedure};
+
+use epoch::{EpochForecast, EpochPreview, EpochProgress, LeaderConcentration, ValidatorSlots};
+use geo::{DataHealth, Network, ValidatorPage};
+use plan::{Plan, PlanWindow, SlotRoute};
+use region::{Fallback, GeoLabel, Region, RegionDistance, RegionScores};
+use retry::{Retry, RetryPolicy};
+use slo::SloReport;
+use span::Span;
+use tpu::TpuEndpoints;
+
+/// RPC client the routing functions take; with the `native` feature it is
+/// solana-client's, so ordinary binaries can call [`route_now`] and
+/// [`route_at`] without the Zela runtime.
+#[cfg(feature = "native")]
+pub use solana_client::nonblocking::rpc_client::RpcClient;
 
 /// Zela procedure entry point.
 pub struct LeaderRouting;
 
-/// Input parameters (currently none required).
+/// What the procedure computes.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Routing decision for the current (or requested) slot.
+    #[default]
+    Current,
+    /// Routing plan for the next `horizon_slots` slots.
+    Plan,
+    /// Geo coverage of the next `sample_slots` leaders against `min_geo_coverage`.
+    SloCheck,
+    /// Region distribution and first leaders of the next epoch.
+    NextEpoch,
+    /// Region distribution of the current epoch's remaining slots.
+    EpochForecast,
+    /// Top `top_n` validators of the current epoch by leader slots.
+    LeaderStats,
+    /// Validators the geo data assigns to `region`, paginated with
+    /// `offset`/`limit`, plus counts for every region.
+    Validators,
+    /// Freshness of the embedded geo data; answered even once it is stale.
+    Health,
+}
+
+/// Input parameters (all optional).
 #[derive(Deserialize, Debug, Default)]
-pub struct Input {}
+pub struct Input {
+    /// What to compute; defaults to the current slot's routing decision.
+    #[serde(default)]
+    pub mode: Mode,
+    /// Answer for this slot instead of the current one (all modes), for
+    /// planning and backtesting. Must be within the leader schedule the RPC
+    /// node holds.
+    pub slot: Option<u64>,
+    /// Commitment of the `getSlot` call that picks the current slot
+    /// (`processed`, `confirmed` or `finalized`); defaults to the client's.
+    /// Leader lookups are unaffected: the schedule does not depend on it.
+    pub commitment: Option<CommitmentLevel>,
+    /// Answer for the slot at this Unix time in milliseconds instead of the
+    /// current one (wherever `slot` applies), estimated from the current slot
+    /// at 400ms per slot. Mutually exclusive with `slot`.
+    pub at_time_ms: Option<u64>,
+    /// Route each of these slots (current mode) and return one result per
+    /// slot, in request order. At most [`plan::MAX_BATCH_SLOTS`].
+    pub slots: Option<Vec<u64>>,
+    /// Slots covered by a plan (`mode: plan`), default
+    /// [`plan::DEFAULT_HORIZON_SLOTS`], at most [`plan::MAX_HORIZON_SLOTS`].
+    pub horizon_slots: Option<u64>,
+    /// Plan length in seconds (converted at 400ms per slot); alternative to
+    /// `horizon_slots`.
+    pub horizon_secs: Option<u64>,
+    /// Leaders sampled by `mode: slo_check`, default
+    /// [`slo::DEFAULT_SAMPLE_SLOTS`], at most [`plan::MAX_SLOT_LEADERS_PER_CALL`].
+    pub sample_slots: Option<u64>,
+    /// Pass threshold for `mode: slo_check`, default
+    /// [`slo::DEFAULT_MIN_GEO_COVERAGE`].
+    pub min_geo_coverage: Option<f64>,
+    /// Caller-supplied trace ID, echoed in logs and the response.
+    /// Generated when absent.
+    pub trace_id: Option<String>,
+    /// Include a per-stage timing breakdown in the output.
+    #[serde(default)]
+    pub debug: bool,
+    /// Destination for leaders that could not be geolocated, as a region
+    /// name, short code or alias. Defaults to the Unknown entry's
+    /// `routes_to` in regions.json, or `LEADER_ROUTING_FALLBACK_REGION` if
+    /// the build set it.
+    pub fallback_region: Option<Region>,
+    /// Include every region's selection score in the output.
+    #[serde(default)]
+    pub scores: bool,
+    /// Also return the next N leader windows after the current one, at most
+    /// [`plan::MAX_LOOKAHEAD`] (current mode).
+    pub lookahead: Option<usize>,
+    /// Slots of leader windows returned by `mode: next_epoch`, default
+    /// [`epoch::DEFAULT_PREVIEW_SLOTS`], at most [`epoch::MAX_PREVIEW_SLOTS`].
+    pub preview_slots: Option<u64>,
+    /// When `ttl_ms` is at most this many milliseconds, also return the next
+    /// leader window's routing as `next` (current mode).
+    pub boundary_window_ms: Option<u64>,
+    /// Regions that are down (names, short codes or aliases). If the chosen
+    /// region is one of them, the first available region of its failover
+    /// chain serves instead, then the next-best ranked one (current mode).
+    #[serde(default)]
+    pub exclude_regions: Vec<Region>,
+    /// Fail with [`ErrorDetail::UnknownLeader`] instead of routing a leader
+    /// that could not be geolocated to the fallback (current mode and batch).
+    #[serde(default)]
+    pub strict_geo: bool,
+    /// Return this validator's upcoming leader slots in the current epoch
+    /// instead of a routing decision (current mode, without `slot` or
+    /// `slots`). Identity pubkey, or a vote account known to the embedded
+    /// validator_info.json.
+    pub validator: Option<String>,
+    /// Region listed by `mode: validators` (name, short code or alias;
+    /// Unknown lists the validators that could not be geolocated).
+    pub region: Option<Region>,
+    /// First validator returned by `mode: validators`, default 0.
+    pub offset: Option<usize>,
+    /// Validators returned by `mode: validators`, default
+    /// [`geo::DEFAULT_PAGE_SIZE`], at most [`geo::MAX_PAGE_SIZE`].
+    pub limit: Option<usize>,
+    /// Validators ranked by `mode: leader_stats`, default
+    /// [`epoch::DEFAULT_TOP_N`], at most [`epoch::MAX_TOP_N`].
+    pub top_n: Option<usize>,
+    /// Include the answered slot's epoch progress (current mode).
+    #[serde(default)]
+    pub epoch_progress: bool,
+    /// Retries of failed `getSlot` / `getSlotLeaders` calls; by default
+    /// [`retry::DEFAULT_MAX_ATTEMPTS`] attempts with
+    /// [`retry::DEFAULT_BASE_DELAY_MS`] base backoff.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+/// Response payload, depending on the requested [`Mode`].
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum Response {
+    Current(Box<Output>),
+    Plan(Plan),
+    SloCheck(SloReport),
+    Batch(Vec<SlotRoute>),
+    NextEpoch(EpochPreview),
+    EpochForecast(EpochForecast),
+    LeaderStats(LeaderConcentration),
+    ValidatorSlots(ValidatorSlots),
+    Validators(ValidatorPage),
+    Health(DataHealth),
+}
 
-/// Output data.
+/// Machine-readable detail attached to every error as `data`, so callers can
+/// decide what to do from `kind` and `retryable` instead of parsing the
+/// message. Serialized as `{"kind": …, "retryable": …, <fields>}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorDetail {
+    /// The input was rejected (code 400); retrying the same request fails
+    /// the same way.
+    InvalidInput,
+    /// An RPC call failed (code 500); usually transient, worth retrying.
+    RpcFailed { method: &'static str },
+    /// The RPC node returned no leader for `slot` (code 404): the slot is
+    /// outside the leader schedule it holds.
+    NoLeader { slot: u64 },
+    /// The RPC node has no leader schedule for `epoch` (slots `first_slot`
+    /// through `last_slot`) yet (code 404); retry later.
+    NoSchedule {
+        epoch: u64,
+        first_slot: u64,
+        last_slot: u64,
+    },
+    /// `strict_geo` was set and the slot's leader could not be geolocated
+    /// (code 422).
+    UnknownLeader { slot: u64, leader: String },
+    /// The embedded geo data is older than the build's
+    /// `LEADER_ROUTING_MAX_DATA_AGE_HOURS` (code 503); redeploy with fresh
+    /// data.
+    StaleData {
+        generated_at: u64,
+        max_age_hours: u64,
+    },
+}
+
+impl ErrorDetail {
+    /// Whether the same request may succeed if retried later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorDetail::RpcFailed { .. } | ErrorDetail::NoSchedule { .. }
+        )
+    }
+
+    /// The serialized `kind` tag.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ErrorDetail::InvalidInput => "invalid_input",
+            ErrorDetail::RpcFailed { .. } => "rpc_failed",
+            ErrorDetail::NoLeader { .. } => "no_leader",
+            ErrorDetail::NoSchedule { .. } => "no_schedule",
+            ErrorDetail::UnknownLeader { .. } => "unknown_leader",
+            ErrorDetail::StaleData { .. } => "stale_data",
+        }
+    }
+}
+
+impl Serialize for ErrorDetail {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("retryable", &self.is_retryable())?;
+        match self {
+            ErrorDetail::InvalidInput => {}
+            ErrorDetail::RpcFailed { method } => map.serialize_entry("method", method)?,
+            ErrorDetail::NoLeader { slot } => map.serialize_entry("slot", slot)?,
+            ErrorDetail::NoSchedule {
+                epoch,
+                first_slot,
+                last_slot,
+            } => {
+                map.serialize_entry("epoch", epoch)?;
+                map.serialize_entry("first_slot", first_slot)?;
+                map.serialize_entry("last_slot", last_slot)?;
+            }
+            ErrorDetail::UnknownLeader { slot, leader } => {
+                map.serialize_entry("slot", slot)?;
+                map.serialize_entry("leader", leader)?;
+            }
+            ErrorDetail::StaleData {
+                generated_at,
+                max_age_hours,
+            } => {
+                map.serialize_entry("generated_at", generated_at)?;
+                map.serialize_entry("max_age_hours", max_age_hours)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// Output data for [`Mode::Current`].
 #[derive(Serialize, Debug)]
 pub struct Output {
     /// Current Solana slot.
@@ -37,60 +304,918 @@ pub struct Output {
     pub leader: String,
     /// Geographic location of the leader.
     pub leader_geo: String,
+    /// Structured form of `leader_geo` (continent, ISO country code, city);
+    /// omitted when the leader could not be geolocated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_location: Option<GeoLabel>,
+    /// ISO country code the leader's IP geolocated to, when the geo data
+    /// records it. Finer than the region: a DE or FR leader is both Frankfurt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_country: Option<&'static str>,
+    /// Name the leader published on chain, when validator_info.json was
+    /// embedded at build time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_name: Option<&'static str>,
+    /// Leader's activated stake in whole SOL, from the same data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_stake_sol: Option<u64>,
+    /// Leader's (most staked) vote account, from the same data, for joining
+    /// with datasets keyed by vote account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_vote_account: Option<String>,
+    /// City the leader's IP geolocated to, when the geo data records it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_city: Option<&'static str>,
+    /// ASN and hosting provider of the leader's IP, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_network: Option<Network>,
+    /// Great-circle distance from the leader to each region, when the geo
+    /// data records the leader's coordinates. The nearest one is the
+    /// preferred region.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_distances: Option<Vec<RegionDistance>>,
+    /// Leader's TPU addresses from the build-time gossip snapshot, when
+    /// data/leader_tpu.json was embedded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_tpu: Option<TpuEndpoints>,
     /// Closest Zela region to the leader.
     pub closest_region: String,
+    /// Canonical short code of `closest_region` (e.g. "fra1").
+    pub code: &'static str,
+    /// Every serving region from best to worst for this leader, with
+    /// `closest_region` first; drained regions are left out. Clients with
+    /// their own constraints can fall back down the list.
+    pub ranked_regions: Vec<Region>,
+    /// Failover chain of `closest_region` from regions.json, in order,
+    /// without drained regions: where to go if it is down.
+    pub fallback_regions: Vec<Region>,
+    /// Fallback rule used when the leader's region is Unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Fallback>,
+    /// First slot after the current leader window; the answer holds until then.
+    pub valid_until_slot: u64,
+    /// Upper bound on how long the answer holds, at 400ms per slot.
+    pub ttl_ms: u64,
+    /// Routing for the first slot of the next leader window, present when
+    /// `ttl_ms` is within `boundary_window_ms`, so clients can hedge.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<SlotRoute>,
+    /// Next `lookahead` leader windows after the current one, with the slot
+    /// each takes over and its serving region.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upcoming: Option<Vec<PlanWindow>>,
+    /// Score of every region for this leader, present when `scores` is set.
+    /// `closest_region` is the one with the lowest score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_scores: Option<RegionScores>,
+    /// Epoch number, slot index, percent complete and time to the epoch
+    /// boundary for `slot`, present when `epoch_progress` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epoch_progress: Option<EpochProgress>,
+    /// Per-stage timings, present when `debug` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<StageTimings>,
+}
+
+/// Time spent in each stage of the routing pipeline.
+///
+/// Response serialization happens in the Zela runtime after `run` returns,
+/// so it is not part of this breakdown.
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub struct StageTimings {
+    /// `getSlot` RPC call, in microseconds.
+    pub get_slot_us: u64,
+    /// `getSlotLeaders` RPC call, in microseconds.
+    pub get_slot_leaders_us: u64,
+    /// PHF geo lookup, in nanoseconds.
+    pub geo_lookup_ns: u64,
+    /// `getSlot` and `getSlotLeaders` calls made, retries included.
+    pub rpc_attempts: u32,
 }
 
+#[cfg(not(feature = "native"))]
 impl CustomProcedure for LeaderRouting {
     type Params = Input;
-    type ErrorData = ();
-    type SuccessData = Output;
+    type ErrorData = ErrorDetail;
+    type SuccessData = Envelope<Response>;
 
-    async fn run(_params: Self::Params) -> Result<Self::SuccessData, RpcError<Self::ErrorData>> {
-        let client = RpcClient::new();
+    async fn run(params: Self::Params) -> Result<Self::SuccessData, RpcError<Self::ErrorData>> {
+        Self::handle(&RpcClient::new(), params).await
+    }
 
-        // Get current slot from RPC (source of truth)
-        let slot = client.get_slot().await.map_err(|e| RpcError {
-            code: 500,
-            message: format!("RPC get_slot failed: {}", e),
-            data: None,
-        })?;
+    const LOG_MAX_LEVEL: log::LevelFilter = if cfg!(feature = "no-logging") {
+        log::LevelFilter::Off
+    } else {
+        log::LevelFilter::Debug
+    };
+}
 
-        // Get leader for this slot
-        let leaders = client
-            .get_slot_leaders(slot, 1)
-            .await
-            .map_err(|e| RpcError {
-                code: 500,
-                message: format!("RPC get_slot_leaders failed: {}", e),
-                data: None,
-            })?;
+impl LeaderRouting {
+    /// Answer a procedure request with `client`; the procedure's `run` calls
+    /// this with the Zela runtime's client.
+    pub async fn handle(
+        client: &RpcClient,
+        params: Input,
+    ) -> Result<Envelope<Response>, RpcError<ErrorDetail>> {
+        let started = Instant::now();
+        let trace_id = common::trace::resolve(params.trace_id.clone());
+        if params.mode != Mode::Health && geo::is_stale(now_ms() / 1000) {
+            return Err(stale_data());
+        }
+        params.retry.validate().map_err(bad_request)?;
+
+        if params.at_time_ms.is_some() {
+            if params.slot.is_some() {
+                return Err(bad_request("slot and at_time_ms are mutually exclusive"));
+            }
+            let takes_slot = match params.mode {
+                Mode::Current => params.validator.is_none() && params.slots.is_none(),
+                Mode::Plan | Mode::SloCheck | Mode::EpochForecast => true,
+                Mode::NextEpoch | Mode::LeaderStats | Mode::Validators | Mode::Health => false,
+            };
+            if !takes_slot {
+                return Err(bad_request(
+                    "at_time_ms only applies to the current (without validator or slots), \
+                     plan, slo_check and epoch_forecast modes",
+                ));
+            }
+        }
+        if params.fallback_region == Some(Region::Unknown) {
+            return Err(bad_request("fallback_region cannot be Unknown"));
+        }
+        if params.slots.is_some() && (params.slot.is_some() || params.mode != Mode::Current) {
+            return Err(bad_request(
+                "slots only applies to the current mode, without slot",
+            ));
+        }
+        if params.validator.is_some()
+            && (params.slot.is_some() || params.slots.is_some() || params.mode != Mode::Current)
+        {
+            return Err(bad_request(
+                "validator only applies to the current mode, without slot or slots",
+            ));
+        }
+
+        // Only the modes that take a slot get here with a timestamp
+        let slot = match params.at_time_ms {
+            Some(at_time_ms) => {
+                let retry = Retry::new(params.retry);
+                let current = retry
+                    .run(|| client.get_slot_with_commitment(commitment(client, &params)))
+                    .await
+                    .map_err(rpc_failed("get_slot"))?;
+                let slot = epoch::slot_at_time(current, now_ms(), at_time_ms);
+                log::debug!(
+                    "trace_id={} at_time_ms={} maps to slot {}",
+                    trace_id,
+                    at_time_ms,
+                    slot
+                );
+                Some(slot)
+            }
+            None => params.slot,
+        };
+
+        let response = match params.mode {
+            Mode::Current if params.validator.is_some() => {
+                Response::ValidatorSlots(validator_slots(client, &params, &trace_id).await?)
+            }
+            Mode::Current if params.slots.is_some() => {
+                Response::Batch(route_batch(client, &params, &trace_id).await?)
+            }
+            Mode::Current => Response::Current(Box::new(
+                route_current(client, &params, slot, &trace_id).await?,
+            )),
+            Mode::Plan => Response::Plan(build_plan(client, &params, slot, &trace_id).await?),
+            Mode::SloCheck => {
+                Response::SloCheck(slo_check(client, &params, slot, &trace_id).await?)
+            }
+            Mode::NextEpoch => Response::NextEpoch(next_epoch(client, &params, &trace_id).await?),
+            Mode::EpochForecast => {
+                Response::EpochForecast(epoch_forecast(client, &params, slot, &trace_id).await?)
+            }
+            Mode::LeaderStats => {
+                Response::LeaderStats(leader_stats(client, &params, &trace_id).await?)
+            }
+            Mode::Validators => Response::Validators(list_validators(&params, &trace_id)?),
+            Mode::Health => Response::Health(geo::health(now_ms() / 1000)),
+        };
+
+        Ok(Envelope::new(provenance(), started, response).with_trace_id(trace_id))
+    }
+}
+
+/// Routing decision for the current slot, as the procedure's default mode
+/// returns it.
+pub async fn route_now(client: &RpcClient) -> Result<Output, RpcError<ErrorDetail>> {
+    route_decision(client, None).await
+}
+
+/// Routing decision for `slot`, which must be within the leader schedule the
+/// RPC node holds.
+pub async fn route_at(client: &RpcClient, slot: u64) -> Result<Output, RpcError<ErrorDetail>> {
+    route_decision(client, Some(slot)).await
+}
+
+async fn route_decision(
+    client: &RpcClient,
+    slot: Option<u64>,
+) -> Result<Output, RpcError<ErrorDetail>> {
+    if geo::is_stale(now_ms() / 1000) {
+        return Err(stale_data());
+    }
+    route_current(
+        client,
+        &Input::default(),
+        slot,
+        &common::trace::resolve(None),
+    )
+    .await
+}
+
+/// Routing decision for the current (or requested) slot.
+async fn route_current(
+    client: &RpcClient,
+    params: &Input,
+    slot: Option<u64>,
+    trace_id: &str,
+) -> Result<Output, RpcError<ErrorDetail>> {
+    let retry = Retry::new(params.retry);
+    let lookahead = params.lookahead.unwrap_or(0);
+    if lookahead > plan::MAX_LOOKAHEAD {
+        return Err(bad_request(format!(
+            "lookahead must be at most {}",
+            plan::MAX_LOOKAHEAD
+        )));
+    }
+
+    let mut timings = StageTimings::default();
+
+    // Get current slot from RPC (source of truth) unless one was requested
+    let span = Span::enter(trace_id, "slot_calc");
+    let slot = start_slot(client, &retry, params, slot).await?;
+    timings.get_slot_us = duration_us(span.end());
 
-        let leader_pubkey = leaders.first().ok_or_else(|| RpcError {
-            code: 404,
-            message: format!("No leader returned for slot {}", slot),
-            data: None,
-        })?;
+    // Near a rotation the next window's first leader is returned too
+    let valid_until_slot = plan::window_end(slot);
+    let ttl_ms = epoch::slot_to_time_ms(valid_until_slot - slot);
+    let hedge = params
+        .boundary_window_ms
+        .is_some_and(|window_ms| ttl_ms <= window_ms);
 
-        let leader_b58 = leader_pubkey.to_string();
-        let leader_bytes: [u8; 32] = leader_pubkey.to_bytes();
-        let region = geo::get_region(&leader_bytes);
+    // Get leader for this slot, plus enough following slots for the lookahead
+    let span = Span::enter(trace_id, "leader_lookup");
+    let wanted = (lookahead as u64 * plan::LEADER_WINDOW_SLOTS + 1).max(if hedge {
+        valid_until_slot - slot + 1
+    } else {
+        1
+    });
+    let mut leaders = fetch_leaders(client, &retry, slot, wanted).await?;
+    let mut upcoming = plan::windows(slot, &leaders, params.fallback_region);
+    // A leader can hold consecutive windows; keep fetching until N distinct ones
+    while let Some((next, count)) =
+        plan::lookahead_refetch(slot, leaders.len(), upcoming.len(), lookahead)
+    {
+        let more = fetch_leaders(client, &retry, next, count).await?;
+        if more.is_empty() {
+            break;
+        }
+        leaders.extend(more);
+        upcoming = plan::windows(slot, &leaders, params.fallback_region);
+    }
+    timings.get_slot_leaders_us = duration_us(span.end());
+
+    let leader_pubkey = leaders.first().ok_or_else(|| no_leader(slot))?;
+
+    let leader_b58 = leader_pubkey.to_string();
+    let leader_bytes: [u8; 32] = leader_pubkey.to_bytes();
+    if params.strict_geo {
+        ensure_located(slot, leader_pubkey)?;
+    }
+    let span = Span::enter(trace_id, "geo_lookup");
+    let region = geo::get_region(&leader_bytes);
+    let country = geo::get_country(&leader_bytes);
+    let coordinates = geo::get_coordinates(&leader_bytes);
+    timings.geo_lookup_ns = u64::try_from(span.end().as_nanos()).unwrap_or(u64::MAX);
+    let (preferred, fallback) = geo::preference(&leader_bytes, params.fallback_region);
+    // Rank from the leader's own location when known, else its region's row
+    let region_scores = match coordinates {
+        Some((lat, lon)) => region::scores_at(lat, lon),
+        None => region::scores(preferred),
+    };
+    let mut ranked_regions = region_scores.ranked();
+    let best = ranked_regions.first().copied().unwrap_or(preferred);
+    let excluded = params.exclude_regions.as_slice();
+    let destination = region::first_available(best, &ranked_regions, excluded)
+        .ok_or_else(|| bad_request("exclude_regions leaves no region to route to"))?;
+    ranked_regions.retain(|r| !excluded.contains(r));
+    if destination != best {
+        log::warn!(
+            "trace_id={} {} is excluded, rerouting to {}",
+            trace_id,
+            best,
+            destination
+        );
+    }
+    let next = hedge
+        .then(|| leaders.get((valid_until_slot - slot) as usize))
+        .flatten()
+        .map(|leader| SlotRoute::new(valid_until_slot, leader, params.fallback_region));
 
-        log::info!("slot={} leader={}... region={}", slot, &leader_b58[..8], region);
+    timings.rpc_attempts = retry.attempts();
 
-        Ok(Output {
+    let epoch_progress = if params.epoch_progress {
+        let span = Span::enter(trace_id, "rpc.get_epoch_info");
+        let info = client
+            .get_epoch_info()
+            .await
+            .map_err(rpc_failed("get_epoch_info"))?;
+        span.end();
+        EpochProgress::at(
             slot,
-            leader: leader_b58,
-            leader_geo: region.geo_label().to_string(),
-            closest_region: region.to_string(),
+            info.epoch,
+            info.absolute_slot - info.slot_index,
+            info.slots_in_epoch,
+        )
+    } else {
+        None
+    };
+
+    log::info!(
+        "trace_id={} slot={} leader={}... name={} region={} destination={}",
+        trace_id,
+        slot,
+        &leader_b58[..8],
+        identity::get_name(&leader_bytes).unwrap_or("-"),
+        region,
+        destination
+    );
+
+    Ok(Output {
+        slot,
+        leader: leader_b58,
+        leader_geo: region.geo_label().to_string(),
+        leader_location: region.location(),
+        leader_country: country,
+        leader_name: identity::get_name(&leader_bytes),
+        leader_stake_sol: identity::get_stake_sol(&leader_bytes),
+        leader_vote_account: identity::get_vote_account(&leader_bytes)
+            .map(|vote| Pubkey::new_from_array(vote).to_string()),
+        leader_city: geo::get_city(&leader_bytes),
+        leader_network: geo::get_network(&leader_bytes),
+        region_distances: coordinates.map(|(lat, lon)| region::distances(lat, lon)),
+        leader_tpu: tpu::get_endpoints(&leader_bytes),
+        closest_region: destination.to_string(),
+        code: destination.short_code().unwrap_or_default(),
+        ranked_regions,
+        fallback_regions: destination
+            .failover()
+            .iter()
+            .copied()
+            .filter(|r| !r.is_drained() && !excluded.contains(r))
+            .collect(),
+        fallback,
+        valid_until_slot,
+        ttl_ms,
+        next,
+        upcoming: (lookahead > 0).then(|| upcoming.into_iter().skip(1).take(lookahead).collect()),
+        region_scores: params.scores.then_some(region_scores),
+        epoch_progress,
+        timings: params.debug.then_some(timings),
+    })
+}
+
+/// Routing for each requested slot, in request order.
+async fn route_batch(
+    client: &RpcClient,
+    params: &Input,
+    trace_id: &str,
+) -> Result<Vec<SlotRoute>, RpcError<ErrorDetail>> {
+    let retry = Retry::new(params.retry);
+    let slots = params.slots.as_deref().unwrap_or_default();
+    if slots.is_empty() || slots.len() > plan::MAX_BATCH_SLOTS {
+        return Err(bad_request(format!(
+            "slots must contain between 1 and {} slots",
+            plan::MAX_BATCH_SLOTS
+        )));
+    }
+    for &slot in slots {
+        check_slot(slot)?;
+    }
+
+    // One getSlotLeaders call per cluster of nearby slots
+    let span = Span::enter(trace_id, "leader_lookup");
+    let mut leaders = HashMap::new();
+    for (start, count) in plan::slot_ranges(slots, plan::MAX_SLOT_LEADERS_PER_CALL) {
+        let range = fetch_leaders(client, &retry, start, count).await?;
+        leaders.extend((start..).zip(range));
+    }
+    span.end();
+
+    let routes = slots
+        .iter()
+        .map(|slot| {
+            let leader = leaders.get(slot).ok_or_else(|| no_leader(*slot))?;
+            if params.strict_geo {
+                ensure_located(*slot, leader)?;
+            }
+            Ok(SlotRoute::new(*slot, leader, params.fallback_region))
         })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    log::info!("trace_id={} batch slots={}", trace_id, routes.len());
+    Ok(routes)
+}
+
+/// Routing plan from the current (or requested) slot for `horizon_slots` slots.
+async fn build_plan(
+    client: &RpcClient,
+    params: &Input,
+    slot: Option<u64>,
+    trace_id: &str,
+) -> Result<Plan, RpcError<ErrorDetail>> {
+    let retry = Retry::new(params.retry);
+    let horizon = plan_horizon(params)?;
+
+    let span = Span::enter(trace_id, "slot_calc");
+    let start_slot = start_slot(client, &retry, params, slot).await?;
+    span.end();
+
+    let span = Span::enter(trace_id, "leader_lookup");
+    let leaders = fetch_leaders(client, &retry, start_slot, horizon).await?;
+    span.end();
+    let windows = plan::windows(start_slot, &leaders, params.fallback_region);
+    log::info!(
+        "trace_id={} plan start_slot={} slots={} windows={}",
+        trace_id,
+        start_slot,
+        leaders.len(),
+        windows.len()
+    );
+
+    let end_slot = start_slot + leaders.len() as u64;
+    Ok(Plan {
+        start_slot,
+        end_slot,
+        segments: plan::segments(&windows, end_slot),
+        windows,
+    })
+}
+
+/// Slots the plan covers, from `horizon_slots` or `horizon_secs`.
+fn plan_horizon(params: &Input) -> Result<u64, RpcError<ErrorDetail>> {
+    let (horizon, out_of_range) = match (params.horizon_slots, params.horizon_secs) {
+        (Some(_), Some(_)) => {
+            return Err(bad_request("pass horizon_slots or horizon_secs, not both"));
+        }
+        (Some(slots), None) => (
+            slots,
+            format!(
+                "horizon_slots must be between 1 and {}",
+                plan::MAX_HORIZON_SLOTS
+            ),
+        ),
+        (None, Some(secs)) => (
+            plan::secs_to_slots(secs),
+            format!(
+                "horizon_secs must be between 1 and {}",
+                plan::MAX_HORIZON_SLOTS * plan::SLOT_DURATION_MS / 1000
+            ),
+        ),
+        (None, None) => return Ok(plan::DEFAULT_HORIZON_SLOTS),
+    };
+    if horizon == 0 || horizon > plan::MAX_HORIZON_SLOTS {
+        return Err(bad_request(out_of_range));
+    }
+    Ok(horizon)
+}
+
+/// `slot` if it is one a request may name (at most [`plan::MAX_SLOT`]).
+fn check_slot(slot: u64) -> Result<u64, RpcError<ErrorDetail>> {
+    if slot > plan::MAX_SLOT {
+        return Err(bad_request(format!(
+            "slot must be at most {}",
+            plan::MAX_SLOT
+        )));
+    }
+    Ok(slot)
+}
+
+/// `slot` if one was requested, or the current one from RPC.
+async fn start_slot(
+    client: &RpcClient,
+    retry: &Retry,
+    params: &Input,
+    slot: Option<u64>,
+) -> Result<u64, RpcError<ErrorDetail>> {
+    match slot {
+        Some(slot) => check_slot(slot),
+        None => retry
+            .run(|| client.get_slot_with_commitment(commitment(client, params)))
+            .await
+            .map_err(rpc_failed("get_slot")),
+    }
+}
+
+/// Commitment for the current slot: the requested level or the client's.
+fn commitment(client: &RpcClient, params: &Input) -> CommitmentConfig {
+    params
+        .commitment
+        .map(|commitment| CommitmentConfig { commitment })
+        .unwrap_or_else(|| client.commitment())
+}
+
+/// Leaders of up to `count` slots from `start_slot`.
+///
+/// getSlotLeaders caps the range per call, so long ranges take several calls.
+/// Stops early if the RPC returns no more leaders.
+async fn fetch_leaders(
+    client: &RpcClient,
+    retry: &Retry,
+    start_slot: u64,
+    count: u64,
+) -> Result<Vec<Pubkey>, RpcError<ErrorDetail>> {
+    let end_slot = start_slot + count;
+    let mut leaders = Vec::with_capacity(count as usize);
+    let mut next = start_slot;
+    while next < end_slot {
+        let limit = (end_slot - next).min(plan::MAX_SLOT_LEADERS_PER_CALL);
+        let chunk = retry
+            .run(|| client.get_slot_leaders(next, limit))
+            .await
+            .map_err(rpc_failed("get_slot_leaders"))?;
+        if chunk.is_empty() {
+            break;
+        }
+        next += chunk.len() as u64;
+        leaders.extend(chunk);
     }
+    Ok(leaders)
+}
+
+/// Geo coverage of leaders from the current (or requested) slot.
+async fn slo_check(
+    client: &RpcClient,
+    params: &Input,
+    slot: Option<u64>,
+    trace_id: &str,
+) -> Result<SloReport, RpcError<ErrorDetail>> {
+    let retry = Retry::new(params.retry);
+    let sample = params.sample_slots.unwrap_or(slo::DEFAULT_SAMPLE_SLOTS);
+    if sample == 0 || sample > plan::MAX_SLOT_LEADERS_PER_CALL {
+        return Err(bad_request(format!(
+            "sample_slots must be between 1 and {}",
+            plan::MAX_SLOT_LEADERS_PER_CALL
+        )));
+    }
+    let min_geo_coverage = params
+        .min_geo_coverage
+        .unwrap_or(slo::DEFAULT_MIN_GEO_COVERAGE);
+    if !(0.0..=1.0).contains(&min_geo_coverage) {
+        return Err(bad_request("min_geo_coverage must be between 0 and 1"));
+    }
+
+    let span = Span::enter(trace_id, "slot_calc");
+    let start_slot = start_slot(client, &retry, params, slot).await?;
+    span.end();
+    let span = Span::enter(trace_id, "leader_lookup");
+    let leaders = retry
+        .run(|| client.get_slot_leaders(start_slot, sample))
+        .await
+        .map_err(rpc_failed("get_slot_leaders"))?;
+    span.end();
+
+    let report = slo::check(start_slot, &leaders, min_geo_coverage);
+    log::info!(
+        "trace_id={} slo_check pass={} geo_coverage={:.3} sampled={}",
+        trace_id,
+        report.pass,
+        report.geo_coverage,
+        report.sampled
+    );
+    Ok(report)
+}
+
+/// Preview of the epoch after the current one, from its leader schedule.
+async fn next_epoch(
+    client: &RpcClient,
+    params: &Input,
+    trace_id: &str,
+) -> Result<EpochPreview, RpcError<ErrorDetail>> {
+    if params.slot.is_some() {
+        return Err(bad_request("slot does not apply to next_epoch"));
+    }
+    let preview_slots = params.preview_slots.unwrap_or(epoch::DEFAULT_PREVIEW_SLOTS);
+    if preview_slots == 0 || preview_slots > epoch::MAX_PREVIEW_SLOTS {
+        return Err(bad_request(format!(
+            "preview_slots must be between 1 and {}",
+            epoch::MAX_PREVIEW_SLOTS
+        )));
+    }
+
+    let span = Span::enter(trace_id, "rpc.get_epoch_info");
+    let info = client
+        .get_epoch_info()
+        .await
+        .map_err(rpc_failed("get_epoch_info"))?;
+    span.end();
+    let first_slot = info.absolute_slot - info.slot_index + info.slots_in_epoch;
+    let span = Span::enter(trace_id, "rpc.get_leader_schedule");
+    let schedule = client
+        .get_leader_schedule(Some(first_slot))
+        .await
+        .map_err(rpc_failed("get_leader_schedule"))?
+        .ok_or_else(|| no_schedule(info.epoch + 1, first_slot, info.slots_in_epoch))?;
+    span.end();
 
-    const LOG_MAX_LEVEL: log::LevelFilter = log::LevelFilter::Debug;
+    // Mainnet epochs after warmup all have the same length
+    let preview = epoch::preview(
+        info.epoch + 1,
+        first_slot,
+        info.slots_in_epoch,
+        &schedule,
+        preview_slots,
+        params.fallback_region,
+    );
+    log::info!(
+        "trace_id={} next_epoch epoch={} first_slot={} validators={}",
+        trace_id,
+        preview.epoch,
+        first_slot,
+        schedule.len()
+    );
+    Ok(preview)
+}
+
+/// Region split of the current epoch from the current slot (or `slot`) to
+/// its end.
+async fn epoch_forecast(
+    client: &RpcClient,
+    params: &Input,
+    slot: Option<u64>,
+    trace_id: &str,
+) -> Result<EpochForecast, RpcError<ErrorDetail>> {
+    let span = Span::enter(trace_id, "rpc.get_epoch_info");
+    let info = client
+        .get_epoch_info()
+        .await
+        .map_err(rpc_failed("get_epoch_info"))?;
+    span.end();
+    let first_slot = info.absolute_slot - info.slot_index;
+    let end_slot = first_slot + info.slots_in_epoch - 1;
+    let start_slot = slot.unwrap_or(info.absolute_slot);
+    if !(first_slot..=end_slot).contains(&start_slot) {
+        return Err(bad_request(format!(
+            "slot must be within the current epoch ({}..={})",
+            first_slot, end_slot
+        )));
+    }
+    let span = Span::enter(trace_id, "rpc.get_leader_schedule");
+    let schedule = client
+        .get_leader_schedule(Some(first_slot))
+        .await
+        .map_err(rpc_failed("get_leader_schedule"))?
+        .ok_or_else(|| no_schedule(info.epoch, first_slot, info.slots_in_epoch))?;
+    span.end();
+
+    let forecast = epoch::forecast(
+        info.epoch,
+        first_slot,
+        info.slots_in_epoch,
+        start_slot,
+        &schedule,
+        params.fallback_region,
+    );
+    log::info!(
+        "trace_id={} epoch_forecast epoch={} start_slot={} remaining={}",
+        trace_id,
+        forecast.epoch,
+        start_slot,
+        forecast.remaining_slots
+    );
+    Ok(forecast)
+}
+
+/// The current epoch's validators with the most leader slots.
+async fn leader_stats(
+    client: &RpcClient,
+    params: &Input,
+    trace_id: &str,
+) -> Result<LeaderConcentration, RpcError<ErrorDetail>> {
+    if params.slot.is_some() {
+        return Err(bad_request("slot does not apply to leader_stats"));
+    }
+    let top_n = params.top_n.unwrap_or(epoch::DEFAULT_TOP_N);
+    if top_n == 0 || top_n > epoch::MAX_TOP_N {
+        return Err(bad_request(format!(
+            "top_n must be between 1 and {}",
+            epoch::MAX_TOP_N
+        )));
+    }
+
+    let span = Span::enter(trace_id, "rpc.get_epoch_info");
+    let info = client
+        .get_epoch_info()
+        .await
+        .map_err(rpc_failed("get_epoch_info"))?;
+    span.end();
+    let first_slot = info.absolute_slot - info.slot_index;
+    let span = Span::enter(trace_id, "rpc.get_leader_schedule");
+    let schedule = client
+        .get_leader_schedule(Some(first_slot))
+        .await
+        .map_err(rpc_failed("get_leader_schedule"))?
+        .ok_or_else(|| no_schedule(info.epoch, first_slot, info.slots_in_epoch))?;
+    span.end();
+
+    let stats = epoch::concentration(info.epoch, &schedule, top_n, params.fallback_region);
+    log::info!(
+        "trace_id={} leader_stats epoch={} validators={} top_share={:.3}",
+        trace_id,
+        stats.epoch,
+        stats.validators,
+        stats
+            .top
+            .last()
+            .map_or(0.0, |leader| leader.cumulative_share)
+    );
+    Ok(stats)
+}
+
+/// Upcoming leader slots of `params.validator` in the current epoch.
+async fn validator_slots(
+    client: &RpcClient,
+    params: &Input,
+    trace_id: &str,
+) -> Result<ValidatorSlots, RpcError<ErrorDetail>> {
+    let validator = params.validator.as_deref().unwrap_or_default();
+    let validator = Pubkey::from_str(validator)
+        .map_err(|_| bad_request(format!("Invalid validator pubkey: {}", validator)))?;
+    let validator = Pubkey::new_from_array(identity::resolve_identity(&validator.to_bytes()));
+
+    let span = Span::enter(trace_id, "rpc.get_epoch_info");
+    let info = client
+        .get_epoch_info()
+        .await
+        .map_err(rpc_failed("get_epoch_info"))?;
+    span.end();
+    let first_slot = info.absolute_slot - info.slot_index;
+    let span = Span::enter(trace_id, "rpc.get_leader_schedule");
+    let schedule = client
+        .get_leader_schedule(Some(first_slot))
+        .await
+        .map_err(rpc_failed("get_leader_schedule"))?
+        .ok_or_else(|| no_schedule(info.epoch, first_slot, info.slots_in_epoch))?;
+    span.end();
+
+    let slots = epoch::upcoming_slots(
+        info.epoch,
+        first_slot,
+        info.absolute_slot,
+        &schedule,
+        &validator,
+        params.fallback_region,
+    );
+    log::info!(
+        "trace_id={} validator_slots validator={} epoch={} upcoming={}",
+        trace_id,
+        slots.validator,
+        slots.epoch,
+        slots.upcoming_slots
+    );
+    Ok(slots)
+}
+
+/// Validators the embedded geo data assigns to `params.region`.
+fn list_validators(params: &Input, trace_id: &str) -> Result<ValidatorPage, RpcError<ErrorDetail>> {
+    let Some(region) = params.region else {
+        return Err(bad_request("region is required for mode validators"));
+    };
+    if params.slot.is_some() {
+        return Err(bad_request("slot does not apply to validators"));
+    }
+    let limit = params.limit.unwrap_or(geo::DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > geo::MAX_PAGE_SIZE {
+        return Err(bad_request(format!(
+            "limit must be between 1 and {}",
+            geo::MAX_PAGE_SIZE
+        )));
+    }
+
+    let page = geo::validator_page(region, params.offset.unwrap_or(0), limit);
+    log::info!(
+        "trace_id={} validators region={} offset={} returned={} total={}",
+        trace_id,
+        region,
+        page.offset,
+        page.validators.len(),
+        page.total
+    );
+    Ok(page)
+}
+
+/// Adapter for `map_err` on RPC calls.
+fn rpc_failed<E: std::fmt::Display>(
+    method: &'static str,
+) -> impl FnOnce(E) -> RpcError<ErrorDetail> {
+    move |e| RpcError {
+        code: 500,
+        message: format!("RPC {} failed: {}", method, e),
+        data: Some(ErrorDetail::RpcFailed { method }),
+    }
+}
+
+/// The RPC node returned no leader for `slot`.
+fn no_leader(slot: u64) -> RpcError<ErrorDetail> {
+    RpcError {
+        code: 404,
+        message: format!("No leader returned for slot {}", slot),
+        data: Some(ErrorDetail::NoLeader { slot }),
+    }
+}
+
+/// The RPC node has no leader schedule for `epoch`.
+fn no_schedule(epoch: u64, first_slot: u64, slots_in_epoch: u64) -> RpcError<ErrorDetail> {
+    let last_slot = first_slot + slots_in_epoch.saturating_sub(1);
+    RpcError {
+        code: 404,
+        message: format!(
+            "No leader schedule for epoch {} (slots {}..={})",
+            epoch, first_slot, last_slot
+        ),
+        data: Some(ErrorDetail::NoSchedule {
+            epoch,
+            first_slot,
+            last_slot,
+        }),
+    }
+}
+
+/// Error for `strict_geo` when `leader` could not be geolocated.
+fn ensure_located(slot: u64, leader: &Pubkey) -> Result<(), RpcError<ErrorDetail>> {
+    if geo::is_located(&leader.to_bytes()) {
+        return Ok(());
+    }
+    Err(RpcError {
+        code: 422,
+        message: format!("Leader {} of slot {} could not be geolocated", leader, slot),
+        data: Some(ErrorDetail::UnknownLeader {
+            slot,
+            leader: leader.to_string(),
+        }),
+    })
+}
+
+/// The embedded geo data is past its age limit.
+fn stale_data() -> RpcError<ErrorDetail> {
+    let generated_at = geo::generated_at().unwrap_or_default();
+    let max_age_hours = geo::max_data_age_hours().unwrap_or_default();
+    RpcError {
+        code: 503,
+        message: format!(
+            "Geo data generated at {} is older than {} hours; redeploy with fresh data",
+            generated_at, max_age_hours
+        ),
+        data: Some(ErrorDetail::StaleData {
+            generated_at,
+            max_age_hours,
+        }),
+    }
+}
+
+/// Invalid input.
+fn bad_request(message: impl Into<String>) -> RpcError<ErrorDetail> {
+    RpcError {
+        code: 400,
+        message: message.into(),
+        data: Some(ErrorDetail::InvalidInput),
+    }
+}
+
+/// `duration` in whole microseconds.
+fn duration_us(duration: std::time::Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Current Unix time in milliseconds.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+/// Build and data provenance reported with every response.
+fn provenance() -> Provenance {
+    Provenance {
+        version: env!("CARGO_PKG_VERSION"),
+        data_hash: geo::data_hash(),
+    }
 }
 
 // Wire up the Zela procedure
-zela_custom_procedure!(LeaderRouting);
+#[cfg(all(feature = "procedure", not(feature = "native")))]
+zela_std::zela_custom_procedure!(LeaderRouting);
 
 #[cfg(test)]
 mod tests {
@@ -102,11 +1227,217 @@ mod tests {
             slot: 12345,
             leader: "abc123".to_string(),
             leader_geo: "Europe/Frankfurt".to_string(),
+            leader_location: Region::Frankfurt.location(),
+            leader_country: Some("FR"),
+            leader_name: None,
+            leader_stake_sol: None,
+            leader_vote_account: None,
+            leader_city: Some("Paris"),
+            leader_network: Some(Network {
+                asn: Some(16276),
+                provider: None,
+            }),
+            region_distances: None,
+            leader_tpu: None,
             closest_region: "Frankfurt".to_string(),
+            code: "fra1",
+            ranked_regions: vec![Region::Frankfurt, Region::Dubai],
+            fallback_regions: Region::Frankfurt.failover().to_vec(),
+            fallback: None,
+            valid_until_slot: 12348,
+            ttl_ms: 1200,
+            next: None,
+            upcoming: None,
+            region_scores: None,
+            epoch_progress: None,
+            timings: None,
         };
 
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("12345"));
+        assert!(json.contains(r#""valid_until_slot":12348"#));
         assert!(json.contains("Frankfurt"));
+        assert!(!json.contains("timings"));
+        assert!(!json.contains(r#""fallback":"#));
+        assert!(json.contains(r#""fallback_regions":["Amsterdam","NewYork"]"#));
+        assert!(!json.contains("region_scores"));
+        assert!(!json.contains("next"));
+        assert!(json.contains(r#""country":"DE""#));
+        assert!(json.contains(r#""leader_network":{"asn":16276}"#));
+    }
+
+    #[test]
+    fn test_fallback_region_input() {
+        let input: Input = serde_json::from_str(r#"{"fallback_region": "Tokyo"}"#).unwrap();
+        assert_eq!(input.fallback_region, Some(Region::Tokyo));
+
+        let input: Input = serde_json::from_str(r#"{"fallback_region": "nyc1"}"#).unwrap();
+        assert_eq!(input.fallback_region, Some(Region::NewYork));
+
+        assert!(serde_json::from_str::<Input>(r#"{"fallback_region": "Mars"}"#).is_err());
+    }
+
+    #[test]
+    fn test_exclude_regions_input() {
+        let input: Input = serde_json::from_str("{}").unwrap();
+        assert!(input.exclude_regions.is_empty());
+
+        let input: Input =
+            serde_json::from_str(r#"{"exclude_regions": ["fra1", "Tokyo"]}"#).unwrap();
+        assert_eq!(
+            input.exclude_regions,
+            vec![Region::Frankfurt, Region::Tokyo]
+        );
+    }
+
+    #[test]
+    fn test_validator_input() {
+        let input: Input =
+            serde_json::from_str(r#"{"validator": "11111111111111111111111111111111"}"#).unwrap();
+        assert_eq!(input.mode, Mode::Current);
+        assert_eq!(
+            input.validator.as_deref(),
+            Some("11111111111111111111111111111111")
+        );
+    }
+
+    #[test]
+    fn test_list_validators_requires_region() {
+        let input: Input = serde_json::from_str(r#"{"mode": "validators"}"#).unwrap();
+        assert_eq!(list_validators(&input, "t").unwrap_err().code, 400);
+
+        let input: Input =
+            serde_json::from_str(r#"{"mode": "validators", "region": "unknown", "limit": 5}"#)
+                .unwrap();
+        let page = list_validators(&input, "t").unwrap();
+        assert_eq!(page.region, Region::Unknown);
+        assert!(page.validators.len() <= 5);
+
+        let input: Input =
+            serde_json::from_str(r#"{"mode": "validators", "region": "fra1", "limit": 0}"#)
+                .unwrap();
+        assert_eq!(list_validators(&input, "t").unwrap_err().code, 400);
+    }
+
+    #[tokio::test]
+    async fn test_at_time_ms_rejected_without_slot() {
+        // Rejected before any RPC call, so the client never connects
+        #[cfg(feature = "native")]
+        let client = RpcClient::new(String::new());
+        #[cfg(not(feature = "native"))]
+        let client = RpcClient::new();
+
+        let input: Input =
+            serde_json::from_str(r#"{"mode": "health", "at_time_ms": 1700000000000}"#).unwrap();
+        let err = LeaderRouting::handle(&client, input).await.unwrap_err();
+        assert_eq!(err.code, 400);
+        assert!(err.message.contains("at_time_ms"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_errors_carry_kind() {
+        let kind = |err: RpcError<ErrorDetail>| serde_json::to_value(err.data).unwrap();
+        assert_eq!(
+            kind(bad_request("x")),
+            serde_json::json!({"kind": "invalid_input", "retryable": false})
+        );
+        assert_eq!(
+            kind(rpc_failed("get_slot")("timeout")),
+            serde_json::json!({"kind": "rpc_failed", "retryable": true, "method": "get_slot"})
+        );
+        assert_eq!(
+            kind(no_leader(5)),
+            serde_json::json!({"kind": "no_leader", "retryable": false, "slot": 5})
+        );
+        assert_eq!(
+            kind(no_schedule(9, 3_888_000, 432_000)),
+            serde_json::json!({
+                "kind": "no_schedule",
+                "retryable": true,
+                "epoch": 9,
+                "first_slot": 3_888_000,
+                "last_slot": 4_319_999
+            })
+        );
+
+        assert!(ErrorDetail::RpcFailed { method: "get_slot" }.is_retryable());
+        assert!(!ErrorDetail::InvalidInput.is_retryable());
+        assert!(!ErrorDetail::NoLeader { slot: 5 }.is_retryable());
+    }
+
+    #[test]
+    fn test_commitment_input() {
+        let input: Input = serde_json::from_str("{}").unwrap();
+        assert_eq!(input.commitment, None);
+        let input: Input = serde_json::from_str(r#"{"commitment": "finalized"}"#).unwrap();
+        assert_eq!(input.commitment, Some(CommitmentLevel::Finalized));
+        assert!(serde_json::from_str::<Input>(r#"{"commitment": "final"}"#).is_err());
+    }
+
+    #[test]
+    fn test_strict_geo_rejects_unknown_leader() {
+        // Test pubkeys are not in the geo map
+        let leader = Pubkey::new_from_array([7; 32]);
+        let err = ensure_located(42, &leader).unwrap_err();
+        assert_eq!(err.code, 422);
+        let data = serde_json::to_value(err.data).unwrap();
+        assert_eq!(data["kind"], "unknown_leader");
+        assert_eq!(data["leader"], leader.to_string());
+        assert_eq!(data["slot"], 42);
+    }
+
+    #[test]
+    fn test_mode_defaults_to_current() {
+        let input: Input = serde_json::from_str("{}").unwrap();
+        assert_eq!(input.mode, Mode::Current);
+
+        let input: Input =
+            serde_json::from_str(r#"{"mode": "plan", "horizon_slots": 100}"#).unwrap();
+        assert_eq!(input.mode, Mode::Plan);
+        assert_eq!(input.horizon_slots, Some(100));
+    }
+
+    #[test]
+    fn test_slot_input() {
+        let input: Input = serde_json::from_str("{}").unwrap();
+        assert_eq!(input.slot, None);
+
+        let input: Input = serde_json::from_str(r#"{"slot": 401344090}"#).unwrap();
+        assert_eq!(input.slot, Some(401344090));
+    }
+
+    #[test]
+    fn test_check_slot_rejects_far_future() {
+        assert_eq!(check_slot(401_344_090).unwrap(), 401_344_090);
+        assert_eq!(check_slot(plan::MAX_SLOT).unwrap(), plan::MAX_SLOT);
+        assert_eq!(check_slot(u64::MAX).unwrap_err().code, 400);
+        // The largest accepted slot still leaves room for window arithmetic
+        assert!(plan::window_end(plan::MAX_SLOT) > plan::MAX_SLOT);
+    }
+
+    #[test]
+    fn test_plan_horizon_bounds() {
+        let input: Input = serde_json::from_str(r#"{"mode": "plan", "horizon_secs": 60}"#).unwrap();
+        assert_eq!(plan_horizon(&input).unwrap(), 150);
+
+        let input: Input =
+            serde_json::from_str(r#"{"mode": "plan", "horizon_secs": 18446744073709552}"#).unwrap();
+        let err = plan_horizon(&input).unwrap_err();
+        assert!(err.message.starts_with("horizon_secs"), "{}", err.message);
+
+        let input: Input = serde_json::from_str(r#"{"mode": "plan", "horizon_slots": 0}"#).unwrap();
+        assert!(plan_horizon(&input)
+            .unwrap_err()
+            .message
+            .starts_with("horizon_slots"));
+    }
+
+    #[test]
+    fn test_debug_input_defaults_off() {
+        let input: Input = serde_json::from_str("{}").unwrap();
+        assert!(!input.debug);
+
+        let input: Input = serde_json::from_str(r#"{"debug": true}"#).unwrap();
+        assert!(input.debug);
     }
 }
@@ -0,0 +1,297 @@
+//! Routing plans: the leader and serving region for upcoming slots.
+//!
+//! A plan lets clients cache routing decisions locally instead of calling
+//! the procedure per transaction. Consecutive slots with the same leader are
+//! collapsed into one window, so a plan is roughly a quarter the size of the
+//! slot range it covers (leaders rotate every 4 slots).
+
+use serde::Serialize;
+use zela_std::rpc_client::Pubkey;
+
+use crate::geo;
+use crate::region::Region;
+
+/// Target slot duration, used to convert `horizon_secs` to slots.
+pub const SLOT_DURATION_MS: u64 = 400;
+
+/// Default plan length: ~1 hour of 400ms slots.
+pub const DEFAULT_HORIZON_SLOTS: u64 = 9_000;
+
+/// Longest plan a single call may request (~4 hours).
+pub const MAX_HORIZON_SLOTS: u64 = 36_000;
+
+/// Consecutive slots each leader holds.
+pub const LEADER_WINDOW_SLOTS: u64 = 4;
+
+/// Most upcoming leader windows the current mode's `lookahead` may request.
+pub const MAX_LOOKAHEAD: usize = 100;
+
+/// Most slots a single batch request may ask about.
+pub const MAX_BATCH_SLOTS: usize = 1_000;
+
+/// Highest slot a request may name (~3,500 years of 400ms slots), far past
+/// any schedule an RPC node holds; keeps slot arithmetic from overflowing.
+pub const MAX_SLOT: u64 = 1 << 38;
+
+/// Most leaders `getSlotLeaders` returns per call.
+pub const MAX_SLOT_LEADERS_PER_CALL: u64 = 5_000;
+
+/// Routing plan for `[start_slot, end_slot)`.
+#[derive(Serialize, Debug)]
+pub struct Plan {
+    /// First slot covered (the requested slot, or the current one).
+    pub start_slot: u64,
+    /// First slot after the plan.
+    pub end_slot: u64,
+    /// Leader windows in slot order.
+    pub windows: Vec<PlanWindow>,
+    /// Region switches: consecutive windows served by the same region merged.
+    pub segments: Vec<RegionSegment>,
+}
+
+/// Slots `[from_slot, to_slot)` all served by one region.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RegionSegment {
+    pub from_slot: u64,
+    /// First slot after the segment.
+    pub to_slot: u64,
+    pub region: Region,
+}
+
+/// Consecutive slots led by one validator.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlanWindow {
+    /// First slot of the window; it lasts until the next window's start.
+    pub window_start_slot: u64,
+    /// Leader validator pubkey (base58 encoded).
+    pub leader: String,
+    /// Serving region for the leader (after fallback and selection).
+    pub region: Region,
+}
+
+/// Routing answer for one slot of a batch request.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SlotRoute {
+    pub slot: u64,
+    /// Leader validator pubkey (base58 encoded).
+    pub leader: String,
+    /// Region the leader geolocates to (may be Unknown).
+    pub leader_region: Region,
+    /// Serving region (after fallback and selection).
+    pub region: Region,
+    /// Canonical short code of `region`.
+    pub code: &'static str,
+}
+
+impl SlotRoute {
+    pub fn new(slot: u64, leader: &Pubkey, fallback: Option<Region>) -> Self {
+        let leader_region = geo::get_region(&leader.to_bytes());
+        let (region, _) = geo::route(&leader.to_bytes(), fallback);
+        SlotRoute {
+            slot,
+            leader: leader.to_string(),
+            leader_region,
+            region,
+            code: region.short_code().unwrap_or_default(),
+        }
+    }
+}
+
+/// Group slots into `(start_slot, count)` ranges of at most `max_span` slots
+/// each, so every range is one getSlotLeaders call.
+pub fn slot_ranges(slots: &[u64], max_span: u64) -> Vec<(u64, u64)> {
+    let mut sorted = slots.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for slot in sorted {
+        match ranges.last_mut() {
+            Some((start, count)) if slot - *start < max_span => *count = slot - *start + 1,
+            _ => ranges.push((slot, 1)),
+        }
+    }
+    ranges
+}
+
+/// Build plan windows from the leaders of consecutive slots starting at
+/// `start_slot`, routing Unknown leaders to `fallback` when given.
+pub fn windows(start_slot: u64, leaders: &[Pubkey], fallback: Option<Region>) -> Vec<PlanWindow> {
+    let mut windows: Vec<PlanWindow> = Vec::new();
+    let mut previous: Option<&Pubkey> = None;
+
+    for (slot, leader) in (start_slot..).zip(leaders) {
+        if previous == Some(leader) {
+            continue;
+        }
+        previous = Some(leader);
+
+        let (region, _) = geo::route(&leader.to_bytes(), fallback);
+        windows.push(PlanWindow {
+            window_start_slot: slot,
+            leader: leader.to_string(),
+            region,
+        });
+    }
+
+    windows
+}
+
+/// Next `(first_slot, count)` of leaders to fetch so that `fetched` leaders
+/// from `start_slot`, currently spanning `windows` windows, reach the current
+/// window plus `lookahead` more. None once they do, or once
+/// [`MAX_SLOT_LEADERS_PER_CALL`] slots have been fetched, so a leader holding
+/// many consecutive windows cannot keep the caller fetching.
+pub fn lookahead_refetch(
+    start_slot: u64,
+    fetched: usize,
+    windows: usize,
+    lookahead: usize,
+) -> Option<(u64, u64)> {
+    let budget = MAX_SLOT_LEADERS_PER_CALL.saturating_sub(fetched as u64);
+    if lookahead == 0 || windows > lookahead || budget == 0 {
+        return None;
+    }
+    let missing = (lookahead + 1 - windows) as u64;
+    Some((
+        start_slot + fetched as u64,
+        (missing * LEADER_WINDOW_SLOTS).min(budget),
+    ))
+}
+
+/// Merge consecutive windows with the same region into segments ending at
+/// `end_slot`.
+pub fn segments(windows: &[PlanWindow], end_slot: u64) -> Vec<RegionSegment> {
+    let mut segments: Vec<RegionSegment> = Vec::new();
+    for window in windows {
+        if let Some(last) = segments.last_mut() {
+            if last.region == window.region {
+                continue;
+            }
+            last.to_slot = window.window_start_slot;
+        }
+        segments.push(RegionSegment {
+            from_slot: window.window_start_slot,
+            to_slot: end_slot,
+            region: window.region,
+        });
+    }
+    segments
+}
+
+/// First slot after the leader window containing `slot`.
+///
+/// Leader windows are aligned to multiples of [`LEADER_WINDOW_SLOTS`].
+pub fn window_end(slot: u64) -> u64 {
+    (slot / LEADER_WINDOW_SLOTS + 1) * LEADER_WINDOW_SLOTS
+}
+
+/// Slots covering `secs` seconds, rounded up.
+pub fn secs_to_slots(secs: u64) -> u64 {
+    secs.saturating_mul(1000).div_ceil(SLOT_DURATION_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_dedupe_consecutive_leaders() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let b = Pubkey::new_from_array([2; 32]);
+        let leaders = [a, a, a, a, b, b, b, b, a];
+
+        let windows = windows(100, &leaders, None);
+
+        let starts: Vec<u64> = windows.iter().map(|w| w.window_start_slot).collect();
+        assert_eq!(starts, vec![100, 104, 108]);
+        assert_eq!(windows[1].leader, b.to_string());
+    }
+
+    #[test]
+    fn test_lookahead_refetch_stops_on_repeated_leader() {
+        let a = Pubkey::new_from_array([1; 32]);
+        let mut leaders = vec![a; 8];
+        let mut calls = 0;
+        while let Some((first_slot, count)) =
+            lookahead_refetch(100, leaders.len(), windows(100, &leaders, None).len(), 3)
+        {
+            assert_eq!(first_slot, 100 + leaders.len() as u64);
+            leaders.extend(std::iter::repeat_n(a, count as usize));
+            calls += 1;
+        }
+        // One leader never yields more windows; fetching stops at the cap
+        assert_eq!(leaders.len() as u64, MAX_SLOT_LEADERS_PER_CALL);
+        assert!(calls > 0);
+
+        assert_eq!(lookahead_refetch(100, 8, 2, 1), None);
+        assert_eq!(lookahead_refetch(100, 8, 1, 0), None);
+        assert_eq!(lookahead_refetch(100, 8, 1, 2), Some((108, 8)));
+    }
+
+    #[test]
+    fn test_segments_merge_same_region() {
+        let window = |slot, region| PlanWindow {
+            window_start_slot: slot,
+            leader: String::new(),
+            region,
+        };
+        let windows = [
+            window(100, Region::Tokyo),
+            window(104, Region::Tokyo),
+            window(108, Region::Dubai),
+            window(112, Region::Tokyo),
+        ];
+
+        let segments = segments(&windows, 116);
+
+        let spans: Vec<(u64, u64, Region)> = segments
+            .iter()
+            .map(|s| (s.from_slot, s.to_slot, s.region))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (100, 108, Region::Tokyo),
+                (108, 112, Region::Dubai),
+                (112, 116, Region::Tokyo),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slot_ranges_group_nearby_slots() {
+        let slots = [1_000_100, 1_000, 1_004, 1_000, 9_000];
+        assert_eq!(
+            slot_ranges(&slots, 5_000),
+            vec![(1_000, 5), (9_000, 1), (1_000_100, 1)]
+        );
+        // A range never spans more than max_span slots
+        assert_eq!(slot_ranges(&[0, 4, 5], 5), vec![(0, 5), (5, 1)]);
+    }
+
+    #[test]
+    fn test_secs_to_slots() {
+        assert_eq!(secs_to_slots(3600), 9000);
+        assert_eq!(secs_to_slots(1), 3);
+        assert!(secs_to_slots(u64::MAX / 1000 + 1) > MAX_HORIZON_SLOTS);
+    }
+
+    #[test]
+    fn test_window_end() {
+        assert_eq!(window_end(100), 104);
+        assert_eq!(window_end(103), 104);
+        assert_eq!(window_end(104), 108);
+    }
+
+    #[test]
+    fn test_windows_apply_fallback() {
+        // Test pubkeys are not in the geo map, so they are Unknown
+        let leaders = [Pubkey::new_from_array([3; 32])];
+        assert_eq!(
+            windows(0, &leaders, Some(Region::Tokyo))[0].region,
+            Region::Tokyo
+        );
+        assert_eq!(windows(0, &leaders, None)[0].region, Region::Frankfurt);
+    }
+}
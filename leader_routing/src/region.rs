@@ -1,79 +1,325 @@
 //! Region definitions for Zela server locations.
 //!
-//! The four Zela regions represent geographic locations where
+//! The Zela regions represent geographic locations where
 //! Zela deploys infrastructure for low-latency Solana access.
+//!
+//! The region list is defined once in `data/regions.json`; build.rs generates
+//! the `Region` enum from it. A region's u8 code (used by the geo PHF map) is
+//! its position in that list. To change the default for unmapped codes or
+//! where Unknown validators route, edit `default` / `routes_to` there; a
+//! deployment can also override the latter at build time with
+//! `LEADER_ROUTING_FALLBACK_REGION`, and a request with `fallback_region`.
+//!
+//! # Capacity-aware selection
+//!
+//! Each region also has a `weight` and a `drain` flag in regions.json. The
+//! routing destination is only a preference: [`select`] scores every
+//! non-drained region by distance from it (see [`crate::distance`]), scaled by
+//! weight, so an overloaded (down-weighted) or drained region sheds traffic to
+//! its nearest neighbour.
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::distance::{haversine_km, DefaultMetric, DistanceMetric, GreatCircle};
+
+// Include the generated Region enum, codes, labels and routing destinations
+include!(concat!(env!("OUT_DIR"), "/region_gen.rs"));
+
+/// Structured geographic label.
+///
+/// Prefer this over parsing [`Region::geo_label`]; `Display` renders the same
+/// "Continent/City" string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GeoLabel {
+    pub continent: &'static str,
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: &'static str,
+    pub city: &'static str,
+}
 
-use serde::Serialize;
+impl std::fmt::Display for GeoLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.continent, self.city)
+    }
+}
 
-/// Zela server regions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
-pub enum Region {
-    Frankfurt,
-    Dubai,
-    NewYork,
-    Tokyo,
-    /// Unknown location - validator could not be geolocated.
-    /// Routes to Frankfurt as default (38% of validators are in EU).
-    Unknown,
+/// Which rule chose the destination for an Unknown validator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fallback {
+    /// `fallback_region` from the request input.
+    Input,
+    /// `routes_to` of the Unknown entry in regions.json.
+    Registry,
 }
 
 impl Region {
-    /// Default fallback region for unknown validator locations.
+    /// Serving region, with `fallback` overriding where Unknown routes.
     ///
-    /// Frankfurt is chosen because:
-    /// - ~38% of Solana validators are in Europe
-    /// - Central position minimizes worst-case latency
-    pub const DEFAULT: Region = Region::Frankfurt;
-
-    /// Human-readable geographic label for the region.
-    pub fn geo_label(&self) -> &'static str {
-        match self {
-            Region::Frankfurt => "Europe/Frankfurt",
-            Region::Dubai => "Middle East/Dubai",
-            Region::NewYork => "North America/New York",
-            Region::Tokyo => "Asia/Tokyo",
-            Region::Unknown => "UNKNOWN",
-        }
+    /// Also returns which fallback rule was applied, or None for regions
+    /// that were geolocated and route to their own destination.
+    pub fn route(&self, fallback: Option<Region>) -> (Region, Option<Fallback>) {
+        let (preferred, applied) = self.preference(fallback);
+        (select(preferred), applied)
     }
 
-    /// Get the routing destination for this region.
-    /// Unknown routes to Frankfurt as default.
-    pub fn routing_destination(&self) -> Region {
-        match self {
-            Region::Unknown => Region::Frankfurt,
-            other => *other,
+    /// Preferred region before capacity-aware selection: the routing
+    /// destination, or the fallback for Unknown.
+    pub fn preference(&self, fallback: Option<Region>) -> (Region, Option<Fallback>) {
+        match (self, fallback) {
+            (Region::Unknown, Some(region)) => (region, Some(Fallback::Input)),
+            (Region::Unknown, None) => (self.routing_destination(), Some(Fallback::Registry)),
+            _ => (self.routing_destination(), None),
         }
     }
 }
 
-impl std::fmt::Display for Region {
+/// One region's standing in a selection.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RegionScore {
+    pub region: Region,
+    /// Distance from the leader's location, or from the preferred region
+    /// when the leader has none, in the metric's unit.
+    pub distance: f64,
+    /// `(distance + baseline) / weight`; lower is better. None when drained.
+    pub score: Option<f64>,
+}
+
+/// Every region's score for one selection, as reported in the output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegionScores {
+    /// Unit of `distance` ("km" or "ms").
+    pub unit: &'static str,
+    /// Regions in code order; regions the metric cannot place are omitted.
+    pub regions: Vec<RegionScore>,
+}
+
+impl RegionScores {
+    /// Non-drained regions from best (lowest score) to worst; ties keep code
+    /// order, so the first entry is the region [`select`] picks.
+    pub fn ranked(&self) -> Vec<Region> {
+        let mut scored: Vec<(Region, f64)> = self
+            .regions
+            .iter()
+            .filter_map(|s| Some((s.region, s.score?)))
+            .collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+        scored.into_iter().map(|(region, _)| region).collect()
+    }
+}
+
+/// `best` unless it is `excluded`; otherwise the first region of its failover
+/// chain that is neither drained nor excluded, then the first non-excluded
+/// region of `ranked`. None when every candidate is excluded.
+pub fn first_available(best: Region, ranked: &[Region], excluded: &[Region]) -> Option<Region> {
+    let failover = best.failover().iter().filter(|r| !r.is_drained());
+    std::iter::once(&best)
+        .chain(failover)
+        .chain(ranked)
+        .copied()
+        .find(|r| !excluded.contains(r))
+}
+
+/// Choose the region that serves traffic whose preferred region is `preferred`,
+/// using the weights and drain flags from regions.json and the build's
+/// [`DefaultMetric`].
+///
+/// With nominal weights and nothing drained this is `preferred` itself.
+pub fn select(preferred: Region) -> Region {
+    select_by(
+        &DefaultMetric::default(),
+        preferred,
+        |r| r.weight(),
+        |r| r.is_drained(),
+    )
+}
+
+/// [`select`] with an explicit metric and weight and drain lookups.
+///
+/// Each non-drained region scores `(distance + metric.baseline()) / weight`
+/// from `preferred`. The lowest score wins; ties go to the lower region code,
+/// so the result is deterministic. Returns `preferred` when no region can be
+/// scored (e.g. it has no location).
+pub fn select_by(
+    metric: &impl DistanceMetric,
+    preferred: Region,
+    weight: impl Fn(Region) -> f64,
+    drained: impl Fn(Region) -> bool,
+) -> Region {
+    best_by(
+        |r| metric.distance(preferred, r),
+        metric.baseline(),
+        weight,
+        drained,
+    )
+    .map(|(r, _)| r)
+    .unwrap_or(preferred)
+}
+
+/// Scores of every region for traffic preferring `preferred`, as computed by
+/// [`select`]. The selected region is the one with the lowest score.
+pub fn scores(preferred: Region) -> RegionScores {
+    let metric = DefaultMetric::default();
+    RegionScores {
+        unit: metric.unit(),
+        regions: score_all(
+            |r| metric.distance(preferred, r),
+            metric.baseline(),
+            |r| r.weight(),
+            |r| r.is_drained(),
+        ),
+    }
+}
+
+/// Scores of every region for traffic from a point (degrees), such as a
+/// geolocated leader, under the build's [`DefaultMetric`].
+///
+/// Unlike [`scores`], two leaders in the same region rank differently when
+/// they are in different places. Leaders without coordinates (Unknown, or
+/// geo data without them) fall back to [`scores`] of their preferred region,
+/// so all of those sharing a fallback get the same ranking.
+pub fn scores_at(lat: f64, lon: f64) -> RegionScores {
+    let metric = DefaultMetric::default();
+    RegionScores {
+        unit: metric.unit(),
+        regions: score_all(
+            |r| metric.distance_from((lat, lon), r),
+            metric.baseline(),
+            |r| r.weight(),
+            |r| r.is_drained(),
+        ),
+    }
+}
+
+/// Nearest serving region to a point, and its great-circle distance in km.
+///
+/// `lat` and `lon` are in degrees. This is the same scoring [`select`] uses:
+/// drained regions are skipped and distance is scaled by region weight, so
+/// with a down-weighted region the result may not be the geometrically
+/// closest one. Use this rather than re-implementing the ranking elsewhere so
+/// decisions stay consistent with the procedure.
+pub fn nearest(lat: f64, lon: f64) -> (Region, f64) {
+    let point = (lat, lon);
+    let distance = |r: Region| Some(haversine_km(point, r.coordinates()?));
+    best_by(
+        distance,
+        GreatCircle.baseline(),
+        |r| r.weight(),
+        |r| r.is_drained(),
+    )
+    .expect("build.rs guarantees an undrained region with coordinates")
+}
+
+/// Great-circle distance from a point to one region.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RegionDistance {
+    pub region: Region,
+    pub distance_km: f64,
+}
+
+/// Great-circle distance from a point (degrees) to every region with
+/// coordinates, in code order.
+pub fn distances(lat: f64, lon: f64) -> Vec<RegionDistance> {
+    Region::ALL
+        .iter()
+        .filter_map(|&region| {
+            let distance_km = haversine_km((lat, lon), region.coordinates()?);
+            Some(RegionDistance {
+                region,
+                distance_km,
+            })
+        })
+        .collect()
+}
+
+/// Geometrically closest region to a point, ignoring weights and drain flags
+/// (those apply afterwards, in [`select`]). Ties go to the lower region code.
+pub fn closest(lat: f64, lon: f64) -> Option<Region> {
+    distances(lat, lon)
+        .into_iter()
+        .min_by(|a, b| a.distance_km.total_cmp(&b.distance_km))
+        .map(|d| d.region)
+}
+
+/// Lowest-scoring non-drained region and its unscaled distance.
+///
+/// Ties go to the lower region code.
+fn best_by(
+    distance: impl Fn(Region) -> Option<f64>,
+    baseline: f64,
+    weight: impl Fn(Region) -> f64,
+    drained: impl Fn(Region) -> bool,
+) -> Option<(Region, f64)> {
+    score_all(distance, baseline, weight, drained)
+        .into_iter()
+        .filter_map(|s| Some((s.region, s.distance, s.score?)))
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(r, d, _)| (r, d))
+}
+
+/// Score every region the distance function can place, in code order.
+///
+/// Score is `(distance + baseline) / weight`, or None for drained regions.
+fn score_all(
+    distance: impl Fn(Region) -> Option<f64>,
+    baseline: f64,
+    weight: impl Fn(Region) -> f64,
+    drained: impl Fn(Region) -> bool,
+) -> Vec<RegionScore> {
+    Region::ALL
+        .iter()
+        .filter_map(|&region| {
+            let distance = distance(region)?;
+            let score = (!drained(region)).then(|| (distance + baseline) / weight(region));
+            Some(RegionScore {
+                region,
+                distance,
+                score,
+            })
+        })
+        .collect()
+}
+
+/// Error parsing a [`Region`] from a string that names no region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRegionError(String);
+
+impl std::fmt::Display for ParseRegionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Region::Frankfurt => write!(f, "Frankfurt"),
-            Region::Dubai => write!(f, "Dubai"),
-            Region::NewYork => write!(f, "NewYork"),
-            Region::Tokyo => write!(f, "Tokyo"),
-            Region::Unknown => write!(f, "Unknown"),
-        }
+        write!(f, "unknown region {:?}", self.0)
     }
 }
 
-impl From<u8> for Region {
-    fn from(value: u8) -> Self {
-        match value {
-            0 => Region::Frankfurt,
-            1 => Region::Dubai,
-            2 => Region::NewYork,
-            3 => Region::Tokyo,
-            4 => Region::Unknown,
-            _ => Region::DEFAULT,
-        }
+impl std::error::Error for ParseRegionError {}
+
+/// Accepts the region name, short code, any alias from regions.json or the
+/// geo label (`Europe/Frankfurt`), case-insensitive.
+impl std::str::FromStr for Region {
+    type Err = ParseRegionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Region::resolve(s.trim()).ok_or_else(|| ParseRegionError(s.to_string()))
+    }
+}
+
+/// Same names as [`FromStr`](std::str::FromStr).
+impl<'de> Deserialize<'de> for Region {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::distance::LatencyMatrix;
 
     #[test]
     fn test_region_display() {
@@ -91,6 +337,19 @@ mod tests {
         assert_eq!(Region::Unknown.geo_label(), "UNKNOWN");
     }
 
+    #[test]
+    fn test_location_matches_label() {
+        let tokyo = Region::Tokyo.location().unwrap();
+        assert_eq!(tokyo.country, "JP");
+        assert_eq!(tokyo.city, "Tokyo");
+        assert_eq!(Region::Unknown.location(), None);
+        for region in Region::ALL {
+            if let Some(location) = region.location() {
+                assert_eq!(location.to_string(), region.geo_label());
+            }
+        }
+    }
+
     #[test]
     fn test_from_u8() {
         assert_eq!(Region::from(0), Region::Frankfurt);
@@ -98,7 +357,13 @@ mod tests {
         assert_eq!(Region::from(2), Region::NewYork);
         assert_eq!(Region::from(3), Region::Tokyo);
         assert_eq!(Region::from(4), Region::Unknown);
+        // Regions added later are appended after Unknown so codes stay stable
+        assert_eq!(Region::from(5), Region::Amsterdam);
+        assert_eq!(Region::from(6), Region::Singapore);
+        assert_eq!(Region::from(7), Region::LosAngeles);
         assert_eq!(Region::from(99), Region::DEFAULT);
+        // LEADER_ROUTING_FALLBACK_REGION moves both, so they always agree
+        assert_eq!(Region::DEFAULT, Region::Unknown.routing_destination());
     }
 
     #[test]
@@ -109,4 +374,217 @@ mod tests {
         // Unknown routes to Frankfurt
         assert_eq!(Region::Unknown.routing_destination(), Region::Frankfurt);
     }
+
+    #[test]
+    fn test_route_fallback() {
+        // Known regions ignore the fallback
+        assert_eq!(
+            Region::Tokyo.route(Some(Region::NewYork)),
+            (Region::Tokyo, None)
+        );
+        // Unknown uses the input fallback when given, else the registry
+        assert_eq!(
+            Region::Unknown.route(Some(Region::NewYork)),
+            (Region::NewYork, Some(Fallback::Input))
+        );
+        assert_eq!(
+            Region::Unknown.route(None),
+            (Region::Frankfurt, Some(Fallback::Registry))
+        );
+    }
+
+    #[test]
+    fn test_select_nominal_keeps_preference() {
+        for region in Region::ALL.into_iter().filter(|&r| r != Region::Unknown) {
+            assert_eq!(select_by(&GreatCircle, region, |_| 1.0, |_| false), region);
+            assert_eq!(
+                select_by(&LatencyMatrix, region, |_| 1.0, |_| false),
+                region
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_drained_sheds_to_nearest() {
+        // Amsterdam is next door to Frankfurt
+        let drained = |r| r == Region::Frankfurt;
+        assert_eq!(
+            select_by(&GreatCircle, Region::Frankfurt, |_| 1.0, drained),
+            Region::Amsterdam
+        );
+        // Without either, Dubai is the closest remaining region
+        let drained = |r| r == Region::Frankfurt || r == Region::Amsterdam;
+        assert_eq!(
+            select_by(&GreatCircle, Region::Frankfurt, |_| 1.0, drained),
+            Region::Dubai
+        );
+        // ...but NewYork is closer by measured latency
+        assert_eq!(
+            select_by(&LatencyMatrix, Region::Frankfurt, |_| 1.0, drained),
+            Region::NewYork
+        );
+    }
+
+    #[test]
+    fn test_select_low_weight_sheds() {
+        // Frankfurt at 0.8 keeps it; halving sheds to Amsterdam (~360 km away)
+        let weight = |w: f64| move |r| if r == Region::Frankfurt { w } else { 1.0 };
+        assert_eq!(
+            select_by(&GreatCircle, Region::Frankfurt, weight(0.8), |_| false),
+            Region::Frankfurt
+        );
+        assert_eq!(
+            select_by(&GreatCircle, Region::Frankfurt, weight(0.5), |_| false),
+            Region::Amsterdam
+        );
+    }
+
+    #[test]
+    fn test_scores_agree_with_select() {
+        for region in Region::ALL.into_iter().filter(|&r| r != Region::Unknown) {
+            let scores = scores(region);
+            assert_eq!(scores.regions.len(), Region::ALL.len() - 1);
+            let best = scores
+                .regions
+                .iter()
+                .filter(|s| s.score.is_some())
+                .min_by(|a, b| a.score.unwrap().total_cmp(&b.score.unwrap()))
+                .unwrap();
+            assert_eq!(best.region, select(region));
+            assert_eq!(scores.ranked()[0], select(region));
+        }
+    }
+
+    #[test]
+    fn test_ranked_orders_by_score() {
+        let score = |region, score| RegionScore {
+            region,
+            distance: 0.0,
+            score,
+        };
+        let scores = RegionScores {
+            unit: "km",
+            regions: vec![
+                score(Region::Frankfurt, Some(3.0)),
+                score(Region::Dubai, None),
+                score(Region::NewYork, Some(1.0)),
+                score(Region::Tokyo, Some(3.0)),
+            ],
+        };
+        assert_eq!(
+            scores.ranked(),
+            vec![Region::NewYork, Region::Frankfurt, Region::Tokyo]
+        );
+    }
+
+    #[test]
+    fn test_scores_at_ranks_from_the_point() {
+        // Just west of Amsterdam: Amsterdam first, though Frankfurt is close
+        let ranked = scores_at(52.3, 4.0).ranked();
+        assert_eq!(ranked.first(), Some(&Region::Amsterdam));
+        // Near Singapore the ranking differs from Tokyo's row
+        let ranked = scores_at(1.3, 103.8).ranked();
+        assert_eq!(ranked.first(), Some(&Region::Singapore));
+        assert_ne!(ranked, scores(Region::Tokyo).ranked());
+    }
+
+    #[test]
+    fn test_nearest() {
+        // Munich -> Frankfurt, ~300 km
+        let (region, km) = nearest(48.14, 11.58);
+        assert_eq!(region, Region::Frankfurt);
+        assert!((250.0..350.0).contains(&km), "{km}");
+        // Paris: Amsterdam (~430 km) beats Frankfurt (~480 km)
+        assert_eq!(nearest(48.86, 2.35).0, Region::Amsterdam);
+        // Seattle: Los Angeles, not New York
+        assert_eq!(nearest(47.61, -122.33).0, Region::LosAngeles);
+        // Exactly at a region
+        let (lat, lon) = Region::NewYork.coordinates().unwrap();
+        assert_eq!(nearest(lat, lon), (Region::NewYork, 0.0));
+    }
+
+    #[test]
+    fn test_closest_and_distances() {
+        // Mumbai is nearest Dubai, Sao Paulo nearest New York
+        assert_eq!(closest(19.08, 72.88), Some(Region::Dubai));
+        assert_eq!(closest(-23.55, -46.63), Some(Region::NewYork));
+
+        let distances = distances(48.86, 2.35);
+        assert_eq!(distances.len(), Region::ALL.len() - 1);
+        assert_eq!(distances[0].region, Region::Frankfurt);
+        assert!((400.0..550.0).contains(&distances[0].distance_km));
+    }
+
+    #[test]
+    fn test_failover_chains() {
+        assert_eq!(
+            Region::Dubai.failover(),
+            [Region::Frankfurt, Region::Singapore]
+        );
+        assert!(Region::Unknown.failover().is_empty());
+        for region in Region::ALL {
+            assert!(!region.failover().contains(&region));
+            assert!(!region.failover().contains(&Region::Unknown));
+        }
+    }
+
+    #[test]
+    fn test_first_available_follows_failover() {
+        let ranked = scores(Region::Dubai).ranked();
+        assert_eq!(
+            first_available(Region::Dubai, &ranked, &[]),
+            Some(Region::Dubai)
+        );
+        // Dubai's chain is Frankfurt, then Singapore
+        assert_eq!(
+            first_available(Region::Dubai, &ranked, &[Region::Dubai]),
+            Some(Region::Frankfurt)
+        );
+        assert_eq!(
+            first_available(Region::Dubai, &ranked, &[Region::Dubai, Region::Frankfurt]),
+            Some(Region::Singapore)
+        );
+        // Past the chain, the ranking decides
+        let down = [Region::Dubai, Region::Frankfurt, Region::Singapore];
+        let next = ranked.iter().copied().find(|r| !down.contains(r));
+        assert_eq!(first_available(Region::Dubai, &ranked, &down), next);
+        assert_eq!(first_available(Region::Dubai, &ranked, &Region::ALL), None);
+    }
+
+    #[test]
+    fn test_short_codes_and_aliases() {
+        assert_eq!(Region::Frankfurt.short_code(), Some("fra1"));
+        assert_eq!(Region::Unknown.short_code(), None);
+        assert_eq!(Region::resolve("nyc1"), Some(Region::NewYork));
+        assert_eq!(Region::resolve("TYO"), Some(Region::Tokyo));
+        assert_eq!(Region::resolve("Dubai"), Some(Region::Dubai));
+        assert_eq!(Region::resolve("mars1"), None);
+        // Every region resolves from its own name and short code
+        for region in Region::ALL {
+            assert_eq!(Region::resolve(region.name()), Some(region));
+            if let Some(code) = region.short_code() {
+                assert_eq!(Region::resolve(code), Some(region));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_str_accepts_geo_label() {
+        assert_eq!("europe/frankfurt".parse(), Ok(Region::Frankfurt));
+        assert_eq!(" AMS1 ".parse(), Ok(Region::Amsterdam));
+        assert_eq!("UNKNOWN".parse(), Ok(Region::Unknown));
+        for region in Region::ALL {
+            assert_eq!(region.geo_label().parse(), Ok(region));
+        }
+        let err = "Mars".parse::<Region>().unwrap_err();
+        assert_eq!(err.to_string(), r#"unknown region "Mars""#);
+    }
+
+    #[test]
+    fn test_codes_round_trip() {
+        // A region's code is its index in regions.json
+        for (code, region) in Region::ALL.iter().enumerate() {
+            assert_eq!(Region::from(code as u8), *region);
+        }
+    }
 }
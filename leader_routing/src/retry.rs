@@ -0,0 +1,195 @@
+//! Retry with exponential backoff for the slot and leader RPC calls.
+//!
+//! Public RPC nodes (mainnet-beta in particular) fail a small share of
+//! requests transiently. `getSlot` and `getSlotLeaders` are read-only, so a
+//! failed call is simply made again after `base_delay_ms * 2^(n-1)` plus up
+//! to `base_delay_ms` of jitter, until `max_attempts` calls have been made.
+//!
+//! Native builds (feature `native`) wait on tokio's timer. The Zela runtime
+//! has no async timer, so procedure builds block the thread for the delay,
+//! stalling anything else polled on it; [`MAX_ATTEMPTS`] and
+//! [`MAX_BASE_DELAY_MS`] bound how long that can be.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+/// Calls per RPC method when the request does not say (two retries).
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Most calls per RPC method a request may ask for.
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry when the request does not say.
+pub const DEFAULT_BASE_DELAY_MS: u64 = 50;
+
+/// Longest base delay a request may ask for.
+pub const MAX_BASE_DELAY_MS: u64 = 1_000;
+
+/// Retry policy from the request input.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total calls per RPC method, including the first; 1 disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles for each further one.
+    pub base_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay_ms: DEFAULT_BASE_DELAY_MS,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Error message if the policy is outside the allowed bounds.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 || self.max_attempts > MAX_ATTEMPTS {
+            return Err(format!(
+                "retry.max_attempts must be between 1 and {}",
+                MAX_ATTEMPTS
+            ));
+        }
+        if self.base_delay_ms > MAX_BASE_DELAY_MS {
+            return Err(format!(
+                "retry.base_delay_ms must be at most {}",
+                MAX_BASE_DELAY_MS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delay before retry number `retry` (1 for the first), with `jitter` in
+    /// `0..=1` scaling the random part.
+    pub fn delay(&self, retry: u32, jitter: f64) -> Duration {
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(1 << retry.saturating_sub(1).min(16));
+        let jitter = (self.base_delay_ms as f64 * jitter.clamp(0.0, 1.0)) as u64;
+        Duration::from_millis(backoff.saturating_add(jitter))
+    }
+}
+
+/// Runs RPC calls under a [`RetryPolicy`], counting every attempt.
+#[derive(Debug, Default)]
+pub struct Retry {
+    policy: RetryPolicy,
+    attempts: Cell<u32>,
+}
+
+impl Retry {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            attempts: Cell::new(0),
+        }
+    }
+
+    /// Calls made so far through this `Retry`, retries included.
+    pub fn attempts(&self) -> u32 {
+        self.attempts.get()
+    }
+
+    /// Call `call` until it succeeds or the policy's attempts run out, and
+    /// return the last result.
+    pub async fn run<T, E, F, Fut>(&self, mut call: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            self.attempts.set(self.attempts.get() + 1);
+            match call().await {
+                Err(_) if attempt < self.policy.max_attempts => {
+                    sleep(self.policy.delay(attempt, jitter())).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Wait `delay` before the next attempt.
+#[cfg(feature = "native")]
+async fn sleep(delay: Duration) {
+    tokio::time::sleep(delay).await;
+}
+
+/// Wait `delay` before the next attempt, blocking (see the module docs).
+#[cfg(not(feature = "native"))]
+async fn sleep(delay: Duration) {
+    std::thread::sleep(delay);
+}
+
+/// Pseudo-random value in `0..1` from the clock; only needs to spread
+/// retries from concurrent callers apart.
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    f64::from(nanos % 1_000) / 1_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_doubles_with_bounded_jitter() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, 0.0), Duration::from_millis(50));
+        assert_eq!(policy.delay(2, 0.0), Duration::from_millis(100));
+        assert_eq!(policy.delay(3, 1.0), Duration::from_millis(250));
+        assert_eq!(policy.delay(3, 7.0), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_validate_bounds() {
+        assert!(RetryPolicy::default().validate().is_ok());
+        let policy = RetryPolicy {
+            max_attempts: 0,
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+        let policy = RetryPolicy {
+            base_delay_ms: 5_000,
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_until_success() {
+        let retry = Retry::new(RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 0,
+        });
+        let mut failures = 2;
+        let result: Result<u32, &str> = retry
+            .run(|| {
+                let outcome = if failures > 0 { Err("busy") } else { Ok(7) };
+                failures -= 1;
+                async move { outcome }
+            })
+            .await;
+        assert_eq!(result, Ok(7));
+        assert_eq!(retry.attempts(), 3);
+
+        let retry = Retry::new(RetryPolicy {
+            max_attempts: 2,
+            base_delay_ms: 0,
+        });
+        let result: Result<u32, &str> = retry.run(|| async { Err("down") }).await;
+        assert_eq!(result, Err("down"));
+        assert_eq!(retry.attempts(), 2);
+    }
+}
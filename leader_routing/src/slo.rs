@@ -0,0 +1,81 @@
+//! Accuracy SLO check: geo coverage of live leaders.
+//!
+//! Same measurement as `test_geo_coverage` in tests/integration_test.rs,
+//! exposed as a procedure mode so monitoring can check the deployed build
+//! and data instead of running cargo tests against production.
+
+use serde::Serialize;
+use zela_std::rpc_client::Pubkey;
+
+use crate::geo;
+use crate::region::Region;
+
+/// Leaders sampled when the request does not say.
+pub const DEFAULT_SAMPLE_SLOTS: u64 = 100;
+
+/// Minimum share of leaders with a known region when the request does not say.
+pub const DEFAULT_MIN_GEO_COVERAGE: f64 = 0.8;
+
+/// Result of an SLO check.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SloReport {
+    /// True when every threshold was met (and real geo data is loaded).
+    pub pass: bool,
+    /// First sampled slot (the requested slot, or the current one).
+    pub start_slot: u64,
+    /// Number of slot leaders sampled.
+    pub sampled: usize,
+    /// Share of sampled leaders with a known region, 0.0-1.0.
+    pub geo_coverage: f64,
+    /// Threshold `geo_coverage` was checked against.
+    pub min_geo_coverage: f64,
+    /// The build has no geo data; the check always fails.
+    pub stub_data: bool,
+}
+
+/// Measure geo coverage over `leaders` and check it against `min_geo_coverage`.
+pub fn check(start_slot: u64, leaders: &[Pubkey], min_geo_coverage: f64) -> SloReport {
+    let known = leaders
+        .iter()
+        .filter(|leader| geo::get_region(&leader.to_bytes()) != Region::Unknown)
+        .count();
+    let geo_coverage = if leaders.is_empty() {
+        0.0
+    } else {
+        known as f64 / leaders.len() as f64
+    };
+    let stub_data = geo::is_stub();
+
+    SloReport {
+        pass: !stub_data && !leaders.is_empty() && geo_coverage >= min_geo_coverage,
+        start_slot,
+        sampled: leaders.len(),
+        geo_coverage,
+        min_geo_coverage,
+        stub_data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_leaders_fail() {
+        // Test pubkeys are not in the geo map
+        let leaders = [
+            Pubkey::new_from_array([1; 32]),
+            Pubkey::new_from_array([2; 32]),
+        ];
+        let report = check(10, &leaders, 0.8);
+        assert_eq!(report.sampled, 2);
+        assert_eq!(report.geo_coverage, 0.0);
+        assert!(!report.pass);
+    }
+
+    #[test]
+    fn test_empty_sample_fails() {
+        let report = check(10, &[], 0.0);
+        assert!(!report.pass);
+    }
+}
@@ -0,0 +1,51 @@
+//! Timed pipeline stages, emitted as structured debug log records.
+//!
+//! Each [`Span`] logs one `span=<stage> elapsed_us=<n>` record tagged with
+//! the request's trace ID when it ends, so slow requests can be attributed
+//! to a stage (slot, leader lookup, geo lookup, individual RPC calls) from
+//! the logs alone. Records are at debug level and vanish with `no-logging`.
+
+use std::time::{Duration, Instant};
+
+/// A pipeline stage being timed.
+#[must_use = "a span only logs when ended"]
+pub struct Span<'a> {
+    trace_id: &'a str,
+    stage: &'static str,
+    started: Instant,
+}
+
+impl<'a> Span<'a> {
+    /// Start timing `stage` of the request with `trace_id`.
+    pub fn enter(trace_id: &'a str, stage: &'static str) -> Self {
+        Self {
+            trace_id,
+            stage,
+            started: Instant::now(),
+        }
+    }
+
+    /// Stop timing, log the record and return the elapsed time.
+    pub fn end(self) -> Duration {
+        let elapsed = self.started.elapsed();
+        log::debug!(
+            "trace_id={} span={} elapsed_us={}",
+            self.trace_id,
+            self.stage,
+            elapsed.as_micros()
+        );
+        elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_measures_elapsed_time() {
+        let span = Span::enter("t", "sleep");
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(span.end() >= Duration::from_millis(2));
+    }
+}
@@ -0,0 +1,40 @@
+//! Leader TPU endpoints from a build-time gossip snapshot.
+//!
+//! Generated by build.rs from the optional data/leader_tpu.json, which
+//! `scripts/precompute_geo.py` writes from the same getClusterNodes call as
+//! the geo data. Without that file every lookup returns None. Validators can
+//! change ports between snapshots, so treat these as hints.
+
+use serde::Serialize;
+
+/// Transaction ingestion addresses (`ip:port`) a validator advertised in gossip.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TpuEndpoints {
+    /// UDP TPU.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tpu: Option<&'static str>,
+    /// QUIC TPU.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tpu_quic: Option<&'static str>,
+    /// TPU forwards port.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tpu_forwards: Option<&'static str>,
+}
+
+// Include the generated PHF map
+include!(concat!(env!("OUT_DIR"), "/phf_tpu.rs"));
+
+/// TPU endpoints of a validator in the snapshot.
+pub fn get_endpoints(pubkey: &[u8; 32]) -> Option<TpuEndpoints> {
+    VALIDATOR_TPU.get(pubkey).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_validator_has_no_endpoints() {
+        assert_eq!(get_endpoints(&[0u8; 32]), None);
+    }
+}
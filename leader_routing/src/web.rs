@@ -0,0 +1,32 @@
+//! Browser bindings for the geo lookup (feature `wasm-bindgen`).
+//!
+//! Build for `wasm32-unknown-unknown` with `--features wasm-bindgen` and run
+//! `wasm-bindgen` over the output to call these from JavaScript. Lookups use
+//! the same embedded geo data and region registry as the procedure.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{geo, region::Region};
+
+/// Region a validator geolocates to ("Unknown" if not in the geo data),
+/// or undefined for an invalid base58 pubkey.
+#[wasm_bindgen(js_name = getRegion)]
+pub fn get_region(pubkey: &str) -> Option<String> {
+    let bytes: [u8; 32] = bs58::decode(pubkey).into_vec().ok()?.try_into().ok()?;
+    Some(geo::get_region(&bytes).to_string())
+}
+
+/// Region the procedure would route a validator's traffic to, after the
+/// Unknown fallback and capacity-aware selection.
+#[wasm_bindgen(js_name = getServingRegion)]
+pub fn get_serving_region(pubkey: &str) -> Option<String> {
+    let bytes: [u8; 32] = bs58::decode(pubkey).into_vec().ok()?.try_into().ok()?;
+    let (region, _) = geo::route(&bytes, None);
+    Some(region.to_string())
+}
+
+/// Short code (e.g. "fra1") for a region name or alias.
+#[wasm_bindgen(js_name = regionCode)]
+pub fn region_code(name: &str) -> Option<String> {
+    Region::resolve(name)?.short_code().map(str::to_string)
+}
@@ -12,8 +12,9 @@ use std::time::Duration;
 /// Solana mainnet RPC endpoint
 const RPC_URL: &str = "https://api.mainnet-beta.solana.com";
 
-/// Minimum acceptable geo coverage (non-Unknown regions)
-const MIN_GEO_COVERAGE: f64 = 0.8; // 80%
+/// Minimum acceptable geo coverage (non-Unknown regions), shared with the
+/// procedure's `slo_check` mode
+const MIN_GEO_COVERAGE: f64 = leader_routing::slo::DEFAULT_MIN_GEO_COVERAGE;
 
 mod helpers {
     use super::*;
@@ -81,6 +82,7 @@ mod helpers {
 #[test]
 fn test_rpc_to_geo_pipeline() {
     use leader_routing::geo;
+    use leader_routing::region::Region;
 
     println!("\n=== RPC to Geo Pipeline Test ===\n");
     println!("Flow: RPC(slot) -> RPC(leader) -> PHF(geo) -> region\n");
@@ -108,9 +110,9 @@ fn test_rpc_to_geo_pipeline() {
     println!("   Routes to:  {}", region.routing_destination());
 
     // Verify valid routing destination
-    let destination = region.routing_destination().to_string();
+    let destination = region.routing_destination();
     assert!(
-        ["Frankfurt", "NewYork", "Tokyo", "Dubai"].contains(&destination.as_str()),
+        destination != Region::Unknown && destination.short_code().is_some(),
         "Invalid routing destination: {}",
         destination
     );
@@ -128,10 +130,14 @@ fn test_geo_coverage() {
     let slot = helpers::get_current_slot().expect("Failed to get current slot");
     let leaders = helpers::get_slot_leaders(slot, 100).expect("Failed to get leaders");
 
-    println!("Testing geo coverage for 100 leaders starting at slot {}", slot);
+    println!(
+        "Testing geo coverage for 100 leaders starting at slot {}",
+        slot
+    );
     println!("{:-<60}", "");
 
-    let mut region_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut region_counts: std::collections::HashMap<String, usize> =
+        std::collections::HashMap::new();
     let mut unknown_count = 0;
     let mut total = 0;
 
@@ -141,12 +147,13 @@ fn test_geo_coverage() {
             _ => continue,
         };
 
-        let region = geo::get_region(&leader_bytes);
-        let region_name = region.to_string();
-
-        if region_name == "Unknown" {
-            unknown_count += 1;
-        }
+        let region_name = match geo::get_region_checked(&leader_bytes) {
+            Some(region) => region.to_string(),
+            None => {
+                unknown_count += 1;
+                "Unknown".to_string()
+            }
+        };
 
         *region_counts.entry(region_name).or_insert(0) += 1;
         total += 1;
@@ -192,7 +199,11 @@ fn test_multiple_slots_geo_lookup() {
     let slot = helpers::get_current_slot().expect("Failed to get slot");
     let leaders = helpers::get_slot_leaders(slot, 50).expect("Failed to get leaders");
 
-    println!("Testing {} live leaders from slot {}...\n", leaders.len(), slot);
+    println!(
+        "Testing {} live leaders from slot {}...\n",
+        leaders.len(),
+        slot
+    );
 
     let mut known = 0;
     let mut unknown = 0;
@@ -227,5 +238,8 @@ fn test_multiple_slots_geo_lookup() {
         MIN_COVERAGE
     );
 
-    println!("\n[OK] Multi-slot geo lookup test PASSED (>= {}%)\n", MIN_COVERAGE);
+    println!(
+        "\n[OK] Multi-slot geo lookup test PASSED (>= {}%)\n",
+        MIN_COVERAGE
+    );
 }
@@ -0,0 +1,88 @@
+//! Python bindings for leader_routing's lookups.
+//!
+//! Wraps the same `geo` and `region` modules the procedure uses, compiled
+//! from the same `data/` files, so Python tooling queries exactly the dataset
+//! that ships in the WASM artifact (compare `data_hash()` with a response's
+//! `data_hash` to be sure).
+//!
+//! Build with `maturin develop` from this directory.
+
+use leader_routing::{geo, region, region::Region};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Decode a base58 validator pubkey.
+fn decode_pubkey(pubkey: &str) -> PyResult<[u8; 32]> {
+    bs58::decode(pubkey)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| PyValueError::new_err(format!("invalid pubkey: {}", pubkey)))
+}
+
+/// Region a validator geolocates to ("Unknown" if not in the geo data).
+#[pyfunction]
+fn get_region(pubkey: &str) -> PyResult<String> {
+    Ok(geo::get_region(&decode_pubkey(pubkey)?).to_string())
+}
+
+/// Region the procedure routes a validator's traffic to, after the Unknown
+/// fallback (optionally overridden) and capacity-aware selection.
+#[pyfunction]
+#[pyo3(signature = (pubkey, fallback=None))]
+fn get_serving_region(pubkey: &str, fallback: Option<&str>) -> PyResult<String> {
+    let fallback = match fallback {
+        Some(name) => match Region::resolve(name) {
+            Some(Region::Unknown) | None => {
+                return Err(PyValueError::new_err(format!("invalid fallback: {}", name)))
+            }
+            region => region,
+        },
+        None => None,
+    };
+    let (region, _) = geo::route(&decode_pubkey(pubkey)?, fallback);
+    Ok(region.to_string())
+}
+
+/// ISO country code a validator geolocated to, if the geo data records one.
+#[pyfunction]
+fn get_country(pubkey: &str) -> PyResult<Option<&'static str>> {
+    Ok(geo::get_country(&decode_pubkey(pubkey)?))
+}
+
+/// Nearest serving region to a point and its distance in km.
+#[pyfunction]
+fn nearest(lat: f64, lon: f64) -> (String, f64) {
+    let (region, km) = region::nearest(lat, lon);
+    (region.to_string(), km)
+}
+
+/// Short code (e.g. "fra1") for a region name or alias.
+#[pyfunction]
+fn region_code(name: &str) -> Option<&'static str> {
+    Region::resolve(name)?.short_code()
+}
+
+/// Number of validators in the embedded geo data.
+#[pyfunction]
+fn validator_count() -> usize {
+    geo::validator_count()
+}
+
+/// SHA-256 of the embedded leader_geo.json, or None for stub builds.
+#[pyfunction]
+fn data_hash() -> Option<&'static str> {
+    geo::data_hash()
+}
+
+#[pymodule]
+fn leader_routing_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_region, m)?)?;
+    m.add_function(wrap_pyfunction!(get_serving_region, m)?)?;
+    m.add_function(wrap_pyfunction!(get_country, m)?)?;
+    m.add_function(wrap_pyfunction!(nearest, m)?)?;
+    m.add_function(wrap_pyfunction!(region_code, m)?)?;
+    m.add_function(wrap_pyfunction!(validator_count, m)?)?;
+    m.add_function(wrap_pyfunction!(data_hash, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! Fee aggregation: [`FeeRecord`]s -> per-block [`BlockFeeStats`] -> [`Output`].
+//!
+//! Pure arithmetic, no RPC or decoding, so it can be tested with
+//! hand-built records.
+
+use serde::{Deserialize, Serialize};
+
+use crate::decode::FeeRecord;
+use crate::{Error, Output};
+
+/// Fee stats of a single block.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockFeeStats {
+	/// Slot of the block.
+	pub slot: u64,
+	/// Number of transactions in the block.
+	pub total_transactions: usize,
+	/// Voting transactions among them.
+	pub vote_transactions: usize,
+	/// Non-voting transactions with a known priority fee.
+	pub fee_transactions: usize,
+	/// Sum of priority fees over `fee_transactions`, in lamports.
+	pub total_priority_fees: u64,
+}
+
+impl BlockFeeStats {
+	/// Summarizes the decoded transactions of block `slot`.
+	pub fn from_records(slot: u64, records: impl IntoIterator<Item = FeeRecord>) -> Self {
+		let mut stats = BlockFeeStats { slot, ..Default::default() };
+		for record in records {
+			stats.total_transactions += 1;
+
+			// count, but otherwise skip, voting transactions
+			if record.is_vote {
+				stats.vote_transactions += 1;
+				continue;
+			}
+			if let Some(priority_fee) = record.priority_fee {
+				stats.total_priority_fees += priority_fee;
+				stats.fee_transactions += 1;
+			}
+		}
+		stats
+	}
+}
+
+/// Mergeable partial aggregate over any set of blocks.
+///
+/// Merging is associative and commutative, so blocks can be summarized on
+/// separate tasks (or separate procedure invocations, via serde) and
+/// reduced in any order to the same result.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FeeAccumulator {
+	total_fees: u64,
+	nonvote_count: usize,
+	total_count: usize,
+	vote_count: usize,
+	latest_block: u64,
+}
+
+impl FeeAccumulator {
+	/// Adds the stats of one block.
+	pub fn add_block(&mut self, block: &BlockFeeStats) {
+		self.merge(&FeeAccumulator::from(block));
+	}
+
+	/// Folds another partial aggregate into this one.
+	pub fn merge(&mut self, other: &FeeAccumulator) {
+		self.latest_block = self.latest_block.max(other.latest_block);
+		self.total_count += other.total_count;
+		self.vote_count += other.vote_count;
+		self.nonvote_count += other.nonvote_count;
+		self.total_fees += other.total_fees;
+	}
+
+	/// By-value [`FeeAccumulator::merge`], for use with `fold`/`reduce`.
+	pub fn combine(mut self, other: FeeAccumulator) -> FeeAccumulator {
+		self.merge(&other);
+		self
+	}
+
+	/// Final stats; fails if no non-voting transaction was seen.
+	pub fn finish(self) -> Result<Output, Error> {
+		if self.nonvote_count == 0 {
+			return Err(Error::EmptySample);
+		}
+
+		Ok(Output {
+			total_transactions: self.total_count,
+			vote_transactions: self.vote_count,
+			latest_block: self.latest_block,
+			average_priority_fee_lamports: self.total_fees / (self.nonvote_count as u64),
+			unserved_blocks: Vec::new(),
+		})
+	}
+}
+
+impl From<&BlockFeeStats> for FeeAccumulator {
+	fn from(block: &BlockFeeStats) -> Self {
+		FeeAccumulator {
+			total_fees: block.total_priority_fees,
+			nonvote_count: block.fee_transactions,
+			total_count: block.total_transactions,
+			vote_count: block.vote_transactions,
+			latest_block: block.slot,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record(is_vote: bool, priority_fee: Option<u64>) -> FeeRecord {
+		FeeRecord { slot: 10, is_vote, priority_fee }
+	}
+
+	#[test]
+	fn test_average_excludes_votes_and_unknown_fees() {
+		let block = BlockFeeStats::from_records(10, [
+			record(true, Some(0)),
+			record(false, Some(300)),
+			record(false, Some(100)),
+			record(false, None),
+		]);
+		assert_eq!(block, BlockFeeStats {
+			slot: 10,
+			total_transactions: 4,
+			vote_transactions: 1,
+			fee_transactions: 2,
+			total_priority_fees: 400,
+		});
+
+		let mut totals = FeeAccumulator::default();
+		totals.add_block(&block);
+
+		assert_eq!(totals.finish().unwrap(), Output {
+			total_transactions: 4,
+			vote_transactions: 1,
+			latest_block: 10,
+			average_priority_fee_lamports: 200,
+			unserved_blocks: vec![],
+		});
+	}
+
+	#[test]
+	fn test_sharded_merge_matches_sequential() {
+		let blocks: Vec<BlockFeeStats> = (0..6u64)
+			.map(|i| BlockFeeStats::from_records(100 + i, [
+				record(i % 2 == 0, Some(i * 1000)),
+				record(false, Some(i * 10)),
+			]))
+			.collect();
+
+		let mut sequential = FeeAccumulator::default();
+		for block in &blocks {
+			sequential.add_block(block);
+		}
+
+		// two shards processed "out of order", reduced the other way round
+		let shard = |blocks: &[BlockFeeStats]| blocks.iter()
+			.map(FeeAccumulator::from)
+			.fold(FeeAccumulator::default(), FeeAccumulator::combine);
+		let merged = shard(&blocks[3..]).combine(shard(&blocks[..3]));
+
+		assert_eq!(merged, sequential);
+		assert_eq!(merged.finish().unwrap().latest_block, 105);
+	}
+
+	#[test]
+	fn test_only_votes_is_empty_sample() {
+		let mut totals = FeeAccumulator::default();
+		totals.add_block(&BlockFeeStats::from_records(10, [record(true, Some(0))]));
+
+		assert_eq!(totals.finish(), Err(Error::EmptySample));
+	}
+}
@@ -0,0 +1,185 @@
+//! Block decoding: RPC transaction encodings -> normalized [`FeeRecord`]s.
+//!
+//! Everything that depends on the shape of a getBlock response lives here,
+//! so the aggregation side only ever sees plain records.
+
+use solana_transaction_status_client_types::{
+	EncodedTransaction, EncodedTransactionWithStatusMeta, UiConfirmedBlock, UiInstruction, UiLoadedAddresses,
+	UiMessage, UiParsedInstruction
+};
+
+/// Base fee every transactions pays.
+pub const BASE_FEE: u64 = 5000;
+/// Vote program id; transactions invoking it are vote transactions.
+pub const VOTE_ACCOUNT: &str = "Vote111111111111111111111111111111111111111";
+
+/// What fee statistics need to know about a single transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRecord {
+	/// Slot of the block containing the transaction.
+	pub slot: u64,
+	/// Whether this is a vote transaction.
+	pub is_vote: bool,
+	/// Fee paid above [`BASE_FEE`], or None when the fee is missing or
+	/// below the base fee.
+	pub priority_fee: Option<u64>
+}
+
+/// Decodes the transactions of block `slot`, skipping any that cannot be
+/// decoded; None if the block has no transaction list.
+pub fn decode_block(slot: u64, block: &UiConfirmedBlock) -> Option<Vec<FeeRecord>> {
+	let records = block_transactions(slot, block)?
+		.iter()
+		.enumerate()
+		.filter_map(|(i, transaction)| decode_transaction(slot, i, transaction))
+		.collect();
+	Some(records)
+}
+
+/// Transactions of block `slot`, or None (logged) if the response has none.
+pub fn block_transactions(slot: u64, block: &UiConfirmedBlock) -> Option<&[EncodedTransactionWithStatusMeta]> {
+	let transactions = block.transactions.as_deref();
+	if transactions.is_none() {
+		log::error!("Transactions not found (block={})", slot);
+	}
+	transactions
+}
+
+/// Decodes transaction `idx` of block `slot`, or None (logged) if it is not
+/// JSON encoded.
+pub fn decode_transaction(
+	slot: u64,
+	idx: usize,
+	transaction: &EncodedTransactionWithStatusMeta
+) -> Option<FeeRecord> {
+	log::trace!("transaction: {transaction:#?}");
+
+	let loaded = transaction.meta.as_ref()
+		.and_then(|m| Option::<&UiLoadedAddresses>::from(m.loaded_addresses.as_ref()));
+	let is_vote = match &transaction.transaction {
+		EncodedTransaction::Json(t) => invokes_vote_program(&t.message, loaded),
+		_ => {
+			log::error!("Transaction account keys not found (block={}, idx={})", slot, idx);
+			return None;
+		}
+	};
+
+	let priority_fee = match &transaction.meta {
+		Some(m) if m.fee < BASE_FEE => {
+			log::error!("Transaction fee less than base fee (block={}, idx={})", slot, idx);
+			None
+		}
+		Some(m) => Some(m.fee - BASE_FEE),
+		None => {
+			log::error!("Transaction fee not found (block={}, idx={})", slot, idx);
+			None
+		}
+	};
+
+	Some(FeeRecord { slot, is_vote, priority_fee })
+}
+
+/// Whether any top-level instruction invokes the vote program.
+///
+/// Raw messages reference programs by index into the static account keys
+/// followed by the lookup-table addresses from `loaded` (writable, then
+/// readonly), which is how v0 messages number their accounts. Parsed
+/// messages already carry resolved program ids.
+fn invokes_vote_program(message: &UiMessage, loaded: Option<&UiLoadedAddresses>) -> bool {
+	match message {
+		UiMessage::Parsed(m) => m.instructions.iter().any(|ix| match ix {
+			UiInstruction::Parsed(UiParsedInstruction::Parsed(p)) => p.program_id == VOTE_ACCOUNT,
+			UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(p)) => p.program_id == VOTE_ACCOUNT,
+			UiInstruction::Compiled(c) => m.account_keys.get(c.program_id_index as usize)
+				.is_some_and(|k| k.pubkey == VOTE_ACCOUNT),
+		}),
+		UiMessage::Raw(m) => {
+			let keys: Vec<&String> = match loaded {
+				Some(l) => m.account_keys.iter().chain(&l.writable).chain(&l.readonly).collect(),
+				None => m.account_keys.iter().collect(),
+			};
+			m.instructions.iter()
+				.any(|ix| keys.get(ix.program_id_index as usize).is_some_and(|k| *k == VOTE_ACCOUNT))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use solana_transaction_status_client_types::{TransactionBinaryEncoding, UiRawMessage, UiTransactionStatusMeta};
+
+	fn fixture_transactions(name: &str) -> Vec<EncodedTransactionWithStatusMeta> {
+		crate::fixtures::block(name).transactions.unwrap()
+	}
+
+	#[test]
+	fn test_decode_vote_and_transfer() {
+		let mut transactions = fixture_transactions("block_380000000.json").into_iter();
+
+		let vote = decode_transaction(7, 0, &transactions.next().unwrap()).unwrap();
+		assert_eq!(vote, FeeRecord { slot: 7, is_vote: true, priority_fee: Some(0) });
+
+		let transfer = decode_transaction(7, 2, &transactions.nth(1).unwrap()).unwrap();
+		assert_eq!(transfer, FeeRecord { slot: 7, is_vote: false, priority_fee: Some(10000) });
+	}
+
+	#[test]
+	fn test_missing_or_short_fee_has_no_priority_fee() {
+		let mut transaction = fixture_transactions("block_380000000.json").remove(2);
+		let mut meta: UiTransactionStatusMeta = transaction.meta.take().unwrap();
+
+		let record = decode_transaction(1, 0, &transaction).unwrap();
+		assert_eq!(record.priority_fee, None);
+
+		meta.fee = BASE_FEE - 1;
+		transaction.meta = Some(meta);
+		let record = decode_transaction(1, 0, &transaction).unwrap();
+		assert_eq!(record.priority_fee, None);
+	}
+
+	fn raw_message(transaction: &mut EncodedTransactionWithStatusMeta) -> &mut UiRawMessage {
+		match &mut transaction.transaction {
+			EncodedTransaction::Json(t) => match &mut t.message {
+				UiMessage::Raw(m) => m,
+				_ => panic!("fixture message is not raw"),
+			},
+			_ => panic!("fixture transaction is not JSON"),
+		}
+	}
+
+	#[test]
+	fn test_vote_program_as_plain_account_is_not_vote() {
+		// transfer that merely lists the vote program among its accounts
+		let mut transaction = fixture_transactions("block_380000000.json").remove(2);
+		raw_message(&mut transaction).account_keys.push(VOTE_ACCOUNT.to_string());
+
+		let record = decode_transaction(1, 0, &transaction).unwrap();
+		assert!(!record.is_vote);
+	}
+
+	#[test]
+	fn test_v0_program_index_resolves_through_loaded_addresses() {
+		// v0 swap: 3 static keys, 2 writable + 1 readonly loaded addresses
+		let mut transaction = fixture_transactions("block_380000001.json").remove(1);
+		assert!(!decode_transaction(1, 1, &transaction).unwrap().is_vote);
+
+		// point an instruction at index 5 (the loaded readonly address) and make that the vote program
+		raw_message(&mut transaction).instructions[1].program_id_index = 5;
+		let meta = transaction.meta.as_mut().unwrap();
+		let mut loaded = Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()).unwrap().clone();
+		loaded.readonly[0] = VOTE_ACCOUNT.to_string();
+		meta.loaded_addresses = Some(loaded).into();
+
+		assert!(decode_transaction(1, 1, &transaction).unwrap().is_vote);
+	}
+
+	#[test]
+	fn test_binary_encoding_is_skipped() {
+		let mut transaction = fixture_transactions("block_380000000.json").remove(0);
+		transaction.transaction = EncodedTransaction::Binary(String::new(), TransactionBinaryEncoding::Base64);
+
+		assert_eq!(decode_transaction(3, 0, &transaction), None);
+	}
+}
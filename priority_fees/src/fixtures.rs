@@ -0,0 +1,10 @@
+//! Test helpers for the getBlock responses in `tests/fixtures`.
+
+use solana_transaction_status_client_types::UiConfirmedBlock;
+
+/// Parses a checked-in getBlock response (json encoding, full details).
+pub fn block(name: &str) -> UiConfirmedBlock {
+	let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+	let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+	serde_json::from_str(&json).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
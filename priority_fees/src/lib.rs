@@ -1,6 +1,25 @@
+//! Priority fee statistics over recent or explicitly listed blocks.
+//!
+//! Usable both as a Zela procedure (wasm32) and as a regular Rust library:
+//! [`PriorityFees::aggregate`] takes an RPC client and returns typed [`Output`]
+//! stats or a typed [`Error`]. [`PriorityFees::stream_blocks`] exposes the
+//! same pipeline as an async stream of per-block stats, and
+//! [`PriorityFees::scan`] runs a custom [`visit::TransactionVisitor`] over it.
+
+pub mod aggregate;
+pub mod decode;
+pub mod visit;
+
+#[cfg(test)]
+mod fixtures;
+
+use aggregate::BlockFeeStats;
+
+use futures_util::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
 
-use solana_transaction_status_client_types::{EncodedTransaction, TransactionDetails, UiMessage, UiTransactionEncoding};
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_transaction_status_client_types::{TransactionDetails, UiConfirmedBlock, UiTransactionEncoding};
 #[cfg(target_arch = "wasm32")]
 use zela_std::rpc_client::{RpcClient, RpcBlockConfig};
 #[cfg(not(target_arch = "wasm32"))]
@@ -9,7 +28,8 @@ use solana_client::{
 	nonblocking::rpc_client::RpcClient
 };
 
-#[derive(Deserialize, Debug)]
+/// Which blocks to aggregate.
+#[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum Input {
 	Latest {
@@ -20,37 +40,95 @@ pub enum Input {
 	}
 }
 
-#[derive(Serialize, Debug)]
+/// Procedure parameters: a block selection plus optional RPC settings.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Params {
+	#[serde(flatten)]
+	pub input: Input,
+	/// Commitment used for every RPC call; defaults to the client's.
+	#[serde(default)]
+	pub commitment: Option<CommitmentLevel>,
+	/// Client-supplied ID echoed in logs and the response envelope;
+	/// generated when absent.
+	#[serde(default)]
+	pub trace_id: Option<String>
+}
+
+/// Aggregated priority fee stats.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Output {
 	/// Total number of transactions scanned.
-	total_transactions: usize,
-	/// Number of transaction skipped because they are voting.
-	vote_transactions: usize,
+	pub total_transactions: usize,
+	/// Number of transactions skipped because they are voting.
+	pub vote_transactions: usize,
 	/// Latest processed block.
-	latest_block: u64,
-	// Average priority fees paid per non-voting transactions
-	average_priority_fee_lamports: u64
+	pub latest_block: u64,
+	/// Average priority fees paid per non-voting transactions
+	pub average_priority_fee_lamports: u64,
+	/// Requested blocks that were not processed because they lie in the
+	/// future or below the RPC's first available block.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub unserved_blocks: Vec<u64>
+}
+
+/// Errors returned by [`PriorityFees::aggregate`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// An RPC call failed.
+	#[error("RPC {method} failed: {message}")]
+	Rpc {
+		method: &'static str,
+		message: String
+	},
+	/// The input does not select any blocks.
+	#[error("invalid input: {0}")]
+	Validation(&'static str),
+	/// None of the explicitly requested blocks can be served; carries them
+	/// all (see [`Output::unserved_blocks`]).
+	#[error("invalid input: none of the requested blocks are available: {0:?}")]
+	Unavailable(Vec<u64>),
+	/// The selected blocks contained no non-voting transactions to average.
+	#[error("no non-voting transactions in the selected blocks")]
+	EmptySample
+}
+
+impl Error {
+	/// `map_err` adapter for RPC client errors.
+	fn rpc<E: std::fmt::Display>(method: &'static str) -> impl FnOnce(E) -> Self {
+		move |e| Error::Rpc { method, message: e.to_string() }
+	}
+
+	/// Error code reported to Zela callers.
+	pub fn code(&self) -> i32 {
+		match self {
+			Error::Validation(_) | Error::Unavailable(_) => 400,
+			Error::EmptySample => 404,
+			Error::Rpc { .. } => 502,
+		}
+	}
 }
 
 pub struct PriorityFees;
 impl PriorityFees {
 	/// Divisor for computing a margin when fetching blocks
 	const BLOCK_COUNT_SLOT_MARGIN_DIV: usize = 10;
-	/// Base fee every transactions pays.
-	const BASE_FEE: u64 = 5000;
-	const VOTE_ACCOUNT: &'static str = "Vote111111111111111111111111111111111111111";
 
-	/// Selects blocks according to input and returns their slot numbers.
-	async fn select_blocks(p: Input, rpc: &RpcClient) -> Result<impl Iterator<Item = u64>, String> {
+	/// Selects blocks according to input.
+	async fn select_blocks(p: Input, rpc: &RpcClient, commitment: CommitmentConfig) -> Result<BlockSelection, Error> {
 		let block_count = match p {
-			// we apply skip here to match the types
-			Input::Specific { blocks } => return Ok(blocks.into_iter().skip(0)),
+			Input::Specific { blocks } if blocks.is_empty() => {
+				return Err(Error::Validation("blocks must not be empty"));
+			}
+			Input::Specific { blocks } => return Self::check_available(blocks, rpc, commitment).await,
+			Input::Latest { block_count: 0 } => {
+				return Err(Error::Validation("block_count must be greater than 0"));
+			}
 			Input::Latest { block_count } => block_count,
 		};
 
 		// start off with some latest slot number - it doesn't need to be the absolute latest,
 		// just needs to be close (at our commitment level)
-		let latest_slot = rpc.get_slot().await.map_err(|e| e.to_string())?;
+		let latest_slot = rpc.get_slot_with_commitment(commitment).await.map_err(Error::rpc("getSlot"))?;
 
 		// find slot numbers of lastest block_count blocks
 		let mut block_slots = Vec::<u64>::new();
@@ -63,120 +141,221 @@ impl PriorityFees {
 			block_slots = rpc.get_blocks_with_commitment(
 				start_slot,
 				None,
-				rpc.commitment()
-			).await.map_err(|e| e.to_string())?;
+				commitment
+			).await.map_err(Error::rpc("getBlocks"))?;
 			log::trace!("get_blocks({start_slot}..) = {}", block_slots.len());
 		}
 		log::info!("Got {} latest blocks: {:?}", block_slots.len(), block_slots);
 
 		let to_skip = block_slots.len() - block_count;
 
-		Ok(block_slots.into_iter().skip(to_skip))
+		Ok(BlockSelection {
+			slots: block_slots.split_off(to_skip),
+			unserved: Vec::new()
+		})
 	}
 
-	pub async fn run(p: Input, rpc: &RpcClient) -> Result<Output, String> {
-		log::debug!("run({p:?})");
+	/// Dedupes an explicit slot list and sets aside slots the RPC cannot serve.
+	async fn check_available(blocks: Vec<u64>, rpc: &RpcClient, commitment: CommitmentConfig) -> Result<BlockSelection, Error> {
+		let latest_slot = rpc.get_slot_with_commitment(commitment).await.map_err(Error::rpc("getSlot"))?;
+		let first_available = rpc.get_first_available_block().await.map_err(Error::rpc("getFirstAvailableBlock"))?;
 
-		let mut total_fees: u64 = 0;
-		let mut nonvote_count: usize = 0;
-		let mut total_count: usize = 0;
-		let mut latest_block: u64 = 0;
+		let selection = BlockSelection::partition(blocks, first_available..=latest_slot);
+		if !selection.unserved.is_empty() {
+			log::warn!("Blocks outside {first_available}..={latest_slot}: {:?}", selection.unserved);
+		}
+		selection.require_served()
+	}
 
-		for slot in Self::select_blocks(p, rpc).await? {
-			log::debug!("Processing block {slot}");
-			let block = rpc.get_block_with_config(
-				slot,
-				RpcBlockConfig {
-					encoding: Some(UiTransactionEncoding::Json),
-					transaction_details: Some(TransactionDetails::Full),
-					rewards: None,
-					commitment: Some(rpc.commitment()),
-					max_supported_transaction_version: Some(0),
-				}
-			).await.map_err(|e| e.to_string())?;
-			let transactions = match block.transactions {
-				Some(t) => t,
-				None => {
-					log::error!("Transactions not found (block={})", slot);
-					continue;
-				}
-			};
-			total_count += transactions.len();
-			latest_block = slot;
-
-			for (i, transaction) in transactions.into_iter().enumerate() {
-				log::trace!("transaction: {transaction:#?}");
-
-				let is_voting = match transaction.transaction {
-					EncodedTransaction::Json(t) => match t.message {
-						UiMessage::Parsed(m) => m.account_keys.iter().any(|k| k.pubkey == Self::VOTE_ACCOUNT),
-						UiMessage::Raw(m) => m.account_keys.iter().any(|k| k == Self::VOTE_ACCOUNT)
-					}
-					_ => {
-						log::error!("Transaction account keys not found (block={}, idx={})", slot, i);
-						continue;
-					}
-				};
-				// skip voting transactions
-				if is_voting {
-					continue;
-				}
+	/// Fetches the selected blocks and aggregates priority fees over their
+	/// non-voting transactions, at the client's commitment level.
+	pub async fn aggregate(p: Input, rpc: &RpcClient) -> Result<Output, Error> {
+		Self::aggregate_with_commitment(p, rpc, rpc.commitment()).await
+	}
 
-				let priority_fee = match transaction.meta {
-					Some(m) if m.fee < Self::BASE_FEE => {
-						log::error!("Transaction fee less than base fee (block={}, idx={})", slot, i);
-						continue;
-					}
-					Some(m) => m.fee - Self::BASE_FEE,
-					None => {
-						log::error!("Transaction fee not found (block={}, idx={})", slot, i);
-						continue;
-					}
-				};
-
-				total_fees += priority_fee;
-				nonvote_count += 1;
-			}
+	/// Same as [`PriorityFees::aggregate`], with an explicit commitment level.
+	pub async fn aggregate_with_commitment(
+		p: Input,
+		rpc: &RpcClient,
+		commitment: CommitmentConfig
+	) -> Result<Output, Error> {
+		log::debug!("aggregate({p:?}, {commitment:?})");
+
+		let FeeStream { unserved_blocks, mut blocks } = Self::stream_blocks(p, rpc, commitment).await?;
+
+		let mut totals = aggregate::FeeAccumulator::default();
+		while let Some(block) = blocks.next().await {
+			totals.add_block(&block?);
 		}
 
-		Ok(Output {
-			total_transactions: total_count,
-			vote_transactions: total_count - nonvote_count,
-			latest_block,
-			average_priority_fee_lamports: total_fees / (nonvote_count as u64),
+		let mut output = totals.finish()?;
+		output.unserved_blocks = unserved_blocks;
+		Ok(output)
+	}
+
+	/// Selects blocks like [`PriorityFees::aggregate`] and returns a stream that
+	/// fetches and summarizes them one at a time, in slot order.
+	///
+	/// A block is only requested when the stream is polled, so dropping the
+	/// stream early (e.g. on a deadline) stops further RPC calls.
+	pub async fn stream_blocks<'a>(
+		p: Input,
+		rpc: &'a RpcClient,
+		commitment: CommitmentConfig
+	) -> Result<FeeStream<'a>, Error> {
+		log::debug!("stream_blocks({p:?}, {commitment:?})");
+
+		let selection = Self::select_blocks(p, rpc, commitment).await?;
+		let blocks = stream::iter(selection.slots)
+			// blocks that came back without transactions are skipped
+			.filter_map(move |slot| async move {
+				Self::fetch_block_stats(slot, rpc, commitment).await.transpose()
+			});
+		#[cfg(not(target_arch = "wasm32"))]
+		let blocks = blocks.boxed();
+		#[cfg(target_arch = "wasm32")]
+		let blocks = blocks.boxed_local();
+
+		Ok(FeeStream {
+			unserved_blocks: selection.unserved,
+			blocks
 		})
 	}
+
+	/// Runs `visitor` over every transaction of the blocks selected by `p`.
+	///
+	/// Returns the requested blocks that were not processed (see
+	/// [`Output::unserved_blocks`]).
+	pub async fn scan<V: visit::TransactionVisitor + ?Sized>(
+		p: Input,
+		rpc: &RpcClient,
+		commitment: CommitmentConfig,
+		visitor: &mut V
+	) -> Result<Vec<u64>, Error> {
+		log::debug!("scan({p:?}, {commitment:?})");
+
+		let selection = Self::select_blocks(p, rpc, commitment).await?;
+		for slot in selection.slots {
+			let block = Self::fetch_block(slot, rpc, commitment).await?;
+			visit::visit_block(slot, &block, visitor);
+		}
+		Ok(selection.unserved)
+	}
+
+	/// Stats of block `slot`, or None if it came back without transactions.
+	async fn fetch_block_stats(
+		slot: u64,
+		rpc: &RpcClient,
+		commitment: CommitmentConfig
+	) -> Result<Option<BlockFeeStats>, Error> {
+		let block = Self::fetch_block(slot, rpc, commitment).await?;
+		Ok(decode::decode_block(slot, &block).map(|records| BlockFeeStats::from_records(slot, records)))
+	}
+
+	async fn fetch_block(slot: u64, rpc: &RpcClient, commitment: CommitmentConfig) -> Result<UiConfirmedBlock, Error> {
+		log::debug!("Processing block {slot}");
+		rpc.get_block_with_config(
+			slot,
+			RpcBlockConfig {
+				encoding: Some(UiTransactionEncoding::Json),
+				transaction_details: Some(TransactionDetails::Full),
+				rewards: None,
+				commitment: Some(commitment),
+				max_supported_transaction_version: Some(0),
+			}
+		).await.map_err(Error::rpc("getBlock"))
+	}
+}
+
+/// Per-block stats stream returned by [`PriorityFees::stream_blocks`].
+pub struct FeeStream<'a> {
+	/// Requested blocks that will not be processed (see [`Output::unserved_blocks`]).
+	pub unserved_blocks: Vec<u64>,
+	/// Stats of each selected block, in slot order.
+	pub blocks: BlockStream<'a>
+}
+
+/// Stream of per-block stats; `Send` except on wasm32, where the Zela
+/// client's futures are not.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BlockStream<'a> = futures_util::stream::BoxStream<'a, Result<BlockFeeStats, Error>>;
+/// Stream of per-block stats; `Send` except on wasm32, where the Zela
+/// client's futures are not.
+#[cfg(target_arch = "wasm32")]
+pub type BlockStream<'a> = futures_util::stream::LocalBoxStream<'a, Result<BlockFeeStats, Error>>;
+
+/// Blocks chosen for processing, plus requested ones that were set aside.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct BlockSelection {
+	slots: Vec<u64>,
+	unserved: Vec<u64>
+}
+
+impl BlockSelection {
+	/// Sorts and dedupes `blocks` (duplicates would double-count fees) and
+	/// splits them by whether they fall inside `available`.
+	fn partition(mut blocks: Vec<u64>, available: std::ops::RangeInclusive<u64>) -> Self {
+		blocks.sort_unstable();
+		blocks.dedup();
+		let (slots, unserved) = blocks.into_iter().partition(|slot| available.contains(slot));
+		BlockSelection { slots, unserved }
+	}
+
+	/// The selection, unless it has no servable block left.
+	fn require_served(self) -> Result<Self, Error> {
+		if self.slots.is_empty() {
+			return Err(Error::Unavailable(self.unserved));
+		}
+		Ok(self)
+	}
 }
 
 #[cfg(target_arch = "wasm32")]
 mod zela {
-	use zela_std::{zela_custom_procedure, CustomProcedure, RpcError};
+	use std::time::Instant;
+
+	use common::{Envelope, Provenance};
+	use zela_std::{CustomProcedure, RpcError};
 
 	use super::*;
 
 	impl CustomProcedure for PriorityFees {
-		type Params = Input;
+		type Params = Params;
 		type ErrorData = ();
-		type SuccessData = Output;
+		type SuccessData = Envelope<Output>;
 
 		// Run method is the entry point of every custom procedure
 		// It will be called once for each incoming request.
 		async fn run(params: Self::Params) -> Result<Self::SuccessData, RpcError<Self::ErrorData>> {
+			let started = Instant::now();
+			let trace_id = common::trace::resolve(params.trace_id.clone());
+			log::debug!("trace_id={trace_id} params: {params:?}");
 			let rpc = RpcClient::new();
 
-			match Self::run(params, &rpc).await {
-				Ok(v) => Ok(v),
-				Err(err) => Err(RpcError {
-					code: 1,
-					message: err,
-					data: None
-				})
+			let provenance = Provenance {
+				version: env!("CARGO_PKG_VERSION"),
+				data_hash: None,
+			};
+			let commitment = params.commitment
+				.map(|commitment| CommitmentConfig { commitment })
+				.unwrap_or_else(|| rpc.commitment());
+			match Self::aggregate_with_commitment(params.input, &rpc, commitment).await {
+				Ok(v) => Ok(Envelope::new(provenance, started, v).with_trace_id(trace_id)),
+				Err(err) => {
+					log::warn!("trace_id={trace_id} {err}");
+					Err(RpcError {
+						code: err.code(),
+						message: err.to_string(),
+						data: None
+					})
+				}
 			}
 		}
 
 		const LOG_MAX_LEVEL: log::LevelFilter = log::LevelFilter::Debug;
 	}
-	zela_custom_procedure!(PriorityFees);
+	#[cfg(feature = "procedure")]
+	zela_std::zela_custom_procedure!(PriorityFees);
 }
 
 #[cfg(test)]
@@ -186,6 +365,111 @@ mod tests {
 	use solana_client::nonblocking::rpc_client::RpcClient;
 	use solana_sdk::commitment_config::CommitmentConfig;
 
+	use crate::aggregate::FeeAccumulator;
+
+	/// Decodes and summarizes a fixture block.
+	fn fixture_stats(slot: u64, name: &str) -> BlockFeeStats {
+		BlockFeeStats::from_records(slot, decode::decode_block(slot, &fixtures::block(name)).unwrap())
+	}
+
+	#[test]
+	fn test_fixture_single_block() {
+		let mut totals = FeeAccumulator::default();
+		totals.add_block(&fixture_stats(380000000, "block_380000000.json"));
+
+		// 2 votes, transfers paying 15000 and 5000 lamports -> priority fees 10000 and 0
+		assert_eq!(totals.finish().unwrap(), Output {
+			total_transactions: 4,
+			vote_transactions: 2,
+			latest_block: 380000000,
+			average_priority_fee_lamports: 5000,
+			unserved_blocks: vec![],
+		});
+	}
+
+	#[test]
+	fn test_fixture_multiple_blocks() {
+		let mut totals = FeeAccumulator::default();
+		totals.add_block(&fixture_stats(380000000, "block_380000000.json"));
+		totals.add_block(&fixture_stats(380000001, "block_380000001.json"));
+
+		// second block adds 1 vote, a v0 swap (100000) and a failed transfer (2500)
+		// (10000 + 0 + 100000 + 2500) / 4
+		assert_eq!(totals.finish().unwrap(), Output {
+			total_transactions: 7,
+			vote_transactions: 3,
+			latest_block: 380000001,
+			average_priority_fee_lamports: 28125,
+			unserved_blocks: vec![],
+		});
+	}
+
+	#[test]
+	fn test_fixture_votes_only_is_empty_sample() {
+		let mut totals = FeeAccumulator::default();
+		totals.add_block(&fixture_stats(380000002, "block_380000002_votes_only.json"));
+
+		assert_eq!(totals.finish(), Err(Error::EmptySample));
+	}
+
+	#[test]
+	fn test_block_without_transactions_is_skipped() {
+		let mut block = fixtures::block("block_380000000.json");
+		block.transactions = None;
+
+		assert_eq!(decode::decode_block(380000000, &block), None);
+	}
+
+	#[test]
+	fn test_params_flatten_block_selection() {
+		let params: Params = serde_json::from_str(r#"{"block_count": 3, "commitment": "finalized"}"#).unwrap();
+		assert!(matches!(params.input, Input::Latest { block_count: 3 }));
+		assert_eq!(params.commitment, Some(CommitmentLevel::Finalized));
+
+		let params: Params = serde_json::from_str(r#"{"blocks": [1, 2]}"#).unwrap();
+		assert!(matches!(params.input, Input::Specific { ref blocks } if blocks == &[1, 2]));
+		assert_eq!(params.commitment, None);
+	}
+
+	#[test]
+	fn test_block_selection_dedupes_and_partitions() {
+		let selection = BlockSelection::partition(vec![30, 10, 20, 10, 5, 40, 30], 10..=30);
+		assert_eq!(selection, BlockSelection {
+			slots: vec![10, 20, 30],
+			unserved: vec![5, 40]
+		});
+	}
+
+	/// Compiles only while the native stream can move across threads.
+	#[allow(dead_code)]
+	fn assert_stream_is_send(stream: FeeStream<'static>) -> impl Send {
+		stream.blocks
+	}
+
+	#[test]
+	fn test_all_unserved_is_unavailable() {
+		let selection = BlockSelection::partition(vec![50, 5, 40], 10..=30);
+		let err = selection.require_served().unwrap_err();
+		assert_eq!(err, Error::Unavailable(vec![5, 40, 50]));
+		assert_eq!(err.code(), Error::Validation("").code());
+
+		let selection = BlockSelection::partition(vec![5, 20], 10..=30);
+		assert_eq!(selection.require_served().unwrap().unserved, vec![5]);
+	}
+
+	#[test]
+	fn test_error_codes_are_distinct() {
+		let errors = [
+			Error::Rpc { method: "getSlot", message: "timeout".to_string() },
+			Error::Validation("blocks must not be empty"),
+			Error::EmptySample,
+		];
+		let mut codes: Vec<i32> = errors.iter().map(Error::code).collect();
+		codes.sort();
+		codes.dedup();
+		assert_eq!(codes.len(), errors.len());
+	}
+
 	#[tokio::test]
 	async fn test_procedure_local() {
 		env_logger::builder()
@@ -198,7 +482,7 @@ mod tests {
 			CommitmentConfig::confirmed(),
 		);
 
-		let out = PriorityFees::run(Input::Latest {
+		let out = PriorityFees::aggregate(Input::Latest {
 			block_count: 1
 		}, &rpc).await.unwrap();
 		log::warn!("Test output: {out:?}");
@@ -0,0 +1,67 @@
+//! Visitor API for custom per-transaction aggregations.
+//!
+//! Block selection, fetching, pagination and decoding stay in this crate;
+//! a [`TransactionVisitor`] only sees each transaction alongside its
+//! decoded [`FeeRecord`]. Use [`crate::PriorityFees::scan`] to drive one
+//! over RPC, or [`visit_block`] for an already fetched block.
+
+use solana_transaction_status_client_types::{EncodedTransactionWithStatusMeta, UiConfirmedBlock};
+
+use crate::decode::{self, FeeRecord};
+
+/// Per-transaction callback plugged into the block-scanning loop.
+pub trait TransactionVisitor {
+	/// Called for every decodable transaction, in block order.
+	fn visit(&mut self, idx: usize, transaction: &EncodedTransactionWithStatusMeta, record: &FeeRecord);
+
+	/// Called after the last transaction of block `slot`.
+	fn end_block(&mut self, _slot: u64) {}
+}
+
+/// Runs `visitor` over every decodable transaction of block `slot`; a block
+/// without a transaction list is skipped entirely.
+pub fn visit_block<V: TransactionVisitor + ?Sized>(slot: u64, block: &UiConfirmedBlock, visitor: &mut V) {
+	let Some(transactions) = decode::block_transactions(slot, block) else {
+		return;
+	};
+	for (idx, transaction) in transactions.iter().enumerate() {
+		if let Some(record) = decode::decode_transaction(slot, idx, transaction) {
+			visitor.visit(idx, transaction, &record);
+		}
+	}
+	visitor.end_block(slot);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Counts failed non-vote transactions per block.
+	#[derive(Default)]
+	struct FailedCounter {
+		current: usize,
+		per_block: Vec<(u64, usize)>
+	}
+
+	impl TransactionVisitor for FailedCounter {
+		fn visit(&mut self, _idx: usize, transaction: &EncodedTransactionWithStatusMeta, record: &FeeRecord) {
+			let failed = transaction.meta.as_ref().is_some_and(|m| m.err.is_some());
+			if failed && !record.is_vote {
+				self.current += 1;
+			}
+		}
+
+		fn end_block(&mut self, slot: u64) {
+			self.per_block.push((slot, std::mem::take(&mut self.current)));
+		}
+	}
+
+	#[test]
+	fn test_visitor_sees_every_transaction() {
+		let mut counter = FailedCounter::default();
+		visit_block(380000000, &crate::fixtures::block("block_380000000.json"), &mut counter);
+		visit_block(380000001, &crate::fixtures::block("block_380000001.json"), &mut counter);
+
+		assert_eq!(counter.per_block, vec![(380000000, 0), (380000001, 1)]);
+	}
+}